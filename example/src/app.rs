@@ -36,16 +36,14 @@ impl vesta::VestaApp for App {
                     push_constant_ranges: &[],
                 });
 
-        let render_pipeline = vesta::RenderPipelineBuilder::new(
-            engine.renderer.surface_config.format,
-            "Render Pipeline",
-        )
-        .with_shader_source(vesta::wgpu::ShaderSource::Wgsl(
-            include_str!("shader.wgsl").into(),
-        ))
-        .with_layout(&render_pipeline_layout)
-        .build(&engine.renderer.device)
-        .unwrap();
+        let render_pipeline =
+            vesta::RenderPipelineBuilder::new(&engine.renderer, "Render Pipeline")
+                .with_shader_source(vesta::wgpu::ShaderSource::Wgsl(
+                    include_str!("shader.wgsl").into(),
+                ))
+                .with_layout(&render_pipeline_layout)
+                .build(&engine.renderer.device)
+                .unwrap();
 
         let cube = Cube::new(&engine.renderer);
 
@@ -60,7 +58,7 @@ impl vesta::VestaApp for App {
                     0.01,
                     1000.0,
                 ),
-                &engine.renderer.device,
+                &engine.renderer,
             );
 
         let camera_controller = vesta::ArcBallCameraController::default();
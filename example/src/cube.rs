@@ -197,7 +197,7 @@ impl Cube {
             "Cube Uniform Buffer",
             vesta::wgpu::ShaderStages::VERTEX,
             uniform_data,
-            &renderer.device,
+            renderer,
         );
 
         let texture = renderer
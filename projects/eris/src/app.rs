@@ -51,14 +51,12 @@ impl vesta::VestaApp for App {
                     push_constant_ranges: &[],
                 });
 
-        let render_pipeline = vesta::RenderPipelineBuilder::new(
-            engine.renderer.surface_config.format,
-            "Main Pipeline",
-        )
-        .with_shader_source(vesta::wgpu::ShaderSource::Wgsl(main_shader_src.into()))
-        .with_layout(&render_pipeline_layout)
-        .build(&engine.renderer.device)
-        .unwrap();
+        let render_pipeline =
+            vesta::RenderPipelineBuilder::new(&engine.renderer, "Main Pipeline")
+                .with_shader_source(vesta::wgpu::ShaderSource::Wgsl(main_shader_src.into()))
+                .with_layout(&render_pipeline_layout)
+                .build(&engine.renderer.device)
+                .unwrap();
 
         // Setup the main camera
         let camera = vesta::CameraBuilder::new()
@@ -71,7 +69,7 @@ impl vesta::VestaApp for App {
                     0.01,
                     10000.0,
                 ),
-                &engine.renderer.device,
+                &engine.renderer,
             );
 
         let camera_controller = vesta::FpsCameraController::default();
@@ -80,7 +78,7 @@ impl vesta::VestaApp for App {
             "Light Uniform Buffer",
             vesta::wgpu::ShaderStages::VERTEX | vesta::wgpu::ShaderStages::FRAGMENT,
             LightUniform::new((2.0, 2.0, 2.0).into(), (1.0, 1.0, 1.0).into()),
-            &engine.renderer.device,
+            &engine.renderer,
         );
 
         // Bodies Setup
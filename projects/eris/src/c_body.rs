@@ -38,7 +38,7 @@ impl CBody {
             "C-Body Uniform Buffer",
             vesta::wgpu::ShaderStages::VERTEX,
             uniform_data,
-            &renderer.device,
+            renderer,
         );
 
         let resolution = 8;
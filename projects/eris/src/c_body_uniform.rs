@@ -67,20 +67,18 @@ impl CelestialBodyPipeline {
                 });
 
         // Main rendering pipeline for celestial bodies
-        let render_pipeline = vesta::RenderPipelineBuilder::new(
-            engine.renderer.surface_config.format,
-            "Celestial Body Shader",
-        )
-        .with_shader_source(vesta::wgpu::ShaderSource::Wgsl(
-            c_body_shader_final_src.clone().into(),
-        ))
-        .with_layout(&render_pipeline_layout)
-        .build(&engine.renderer.device)
-        .unwrap();
+        let render_pipeline =
+            vesta::RenderPipelineBuilder::new(&engine.renderer, "Celestial Body Shader")
+                .with_shader_source(vesta::wgpu::ShaderSource::Wgsl(
+                    c_body_shader_final_src.clone().into(),
+                ))
+                .with_layout(&render_pipeline_layout)
+                .build(&engine.renderer.device)
+                .unwrap();
 
         // Identical to the render pipeline, but with a typology of LineList
         let outline_render_pipeline = vesta::RenderPipelineBuilder::new(
-            engine.renderer.surface_config.format,
+            &engine.renderer,
             "Celestial Body Shader (Outline)",
         )
         .with_shader_source(vesta::wgpu::ShaderSource::Wgsl(
@@ -20,7 +20,7 @@ pub struct App {
 
 impl vesta::VestaApp for App {
     fn init(engine: &mut Engine) -> Self {
-        // Create a layout with two uniform buffers (camera, and model)
+        // Create a layout with two uniform buffers (camera, and the model arena)
         let render_pipeline_layout =
             engine
                 .renderer
@@ -32,7 +32,7 @@ impl vesta::VestaApp for App {
                             vesta::wgpu::ShaderStages::VERTEX,
                             &engine.renderer.device,
                         ),
-                        &vesta::UniformBufferUtils::create_bind_group_layout(
+                        &vesta::UniformBufferUtils::create_dynamic_bind_group_layout(
                             vesta::wgpu::ShaderStages::VERTEX,
                             &engine.renderer.device,
                         ),
@@ -42,17 +42,15 @@ impl vesta::VestaApp for App {
                 });
 
         // Pipeline / shader for pixels
-        let pixel_pipeline = vesta::RenderPipelineBuilder::new(
-            engine.renderer.surface_config.format,
-            "Main Pipeline",
-        )
-        .with_shader_source(vesta::wgpu::ShaderSource::Wgsl(
-            include_str!("resources/pixel_shader.wgsl").into(),
-        ))
-        .with_layout(&render_pipeline_layout)
-        .with_cull_mode(None) // TODO: Fix rendering and remove this
-        .build(&engine.renderer.device)
-        .unwrap();
+        let pixel_pipeline =
+            vesta::RenderPipelineBuilder::new(&engine.renderer, "Main Pipeline")
+                .with_shader_source(vesta::wgpu::ShaderSource::Wgsl(
+                    include_str!("resources/pixel_shader.wgsl").into(),
+                ))
+                .with_layout(&render_pipeline_layout)
+                .with_cull_mode(None) // TODO: Fix rendering and remove this
+                .build(&engine.renderer.device)
+                .unwrap();
 
         // Camera which will let us see around the world
         let camera = vesta::CameraBuilder::new()
@@ -64,7 +62,7 @@ impl vesta::VestaApp for App {
                     0.0001,
                     1000.0,
                 ),
-                &engine.renderer.device,
+                &engine.renderer,
             );
 
         let camera_controller = vesta::FpsCameraController::default();
@@ -1,6 +1,3 @@
-use std::mem::ManuallyDrop;
-use std::num::NonZeroU32;
-
 use rand::rngs::ThreadRng;
 use rand::Rng;
 use vesta::cgmath::{Matrix3, Matrix4, Quaternion, SquareMatrix, Vector2, Vector3};
@@ -18,18 +15,20 @@ pub const GRAVITY: f32 = -9.8;
 pub struct Chunk {
     pub position: Vector2<f32>,
     texture_mesh: vesta::Mesh,
-    texture: vesta::wgpu::Texture,
-    texture_bind_group: vesta::wgpu::BindGroup,
-    uniform_buffer: vesta::UniformBuffer<vesta::ModelUniform>,
+    texture: vesta::DynamicTexture,
+    uniform_slot: vesta::UniformSlot,
     data: Vec<Pixel>,
-    color_buffer: Box<[u8; CHUNK_SIZE as usize * CHUNK_SIZE as usize * 4]>, //Vec<u8>,
     loaded: bool,
     rng: ThreadRng,
-    dirty: bool,
 }
 
 impl Chunk {
-    pub fn new(renderer: &vesta::Renderer, position: Vector2<f32>, _seed: u64) -> Self {
+    pub fn new(
+        renderer: &vesta::Renderer,
+        uniforms: &mut vesta::UniformArena<vesta::ModelUniform>,
+        position: Vector2<f32>,
+        _seed: u64,
+    ) -> Self {
         // Simple square which the texture will be rendered onto
         let mut vertices = Vec::new();
         vertices.push(Self::create_vertex(1.0, 1.0, 1.0, 0.0)); // Top Right      1,1   0,1   0,0   1,0   1,1
@@ -51,64 +50,22 @@ impl Chunk {
             model,
             normal: Matrix3::identity(),
         };
-        let uniform_buffer = vesta::UniformBuffer::new(
-            "Chunk Uniform Buffer",
-            vesta::wgpu::ShaderStages::VERTEX,
-            uniform_data,
-            &renderer.device,
-        );
+        let uniform_slot = uniforms.allocate(&uniform_data, renderer);
 
         // Texture that data will be written to
-        let texture_size = vesta::wgpu::Extent3d {
-            width: CHUNK_SIZE as u32,
-            height: CHUNK_SIZE as u32,
-            depth_or_array_layers: 1,
-        };
-
         let texture = renderer
-            .device
-            .create_texture(&vesta::wgpu::TextureDescriptor {
-                label: Some("Chunk Texture"),
-                size: texture_size,
-                mip_level_count: 1,
-                sample_count: 1,
-                dimension: vesta::wgpu::TextureDimension::D2,
-                format: vesta::wgpu::TextureFormat::Rgba8UnormSrgb,
-                usage: vesta::wgpu::TextureUsages::TEXTURE_BINDING
-                    | vesta::wgpu::TextureUsages::COPY_DST,
-            });
-
-        let view = texture.create_view(&vesta::wgpu::TextureViewDescriptor::default());
-        let sampler = renderer
-            .device
-            .create_sampler(&vesta::wgpu::SamplerDescriptor {
-                address_mode_u: vesta::wgpu::AddressMode::ClampToEdge,
-                address_mode_v: vesta::wgpu::AddressMode::ClampToEdge,
-                address_mode_w: vesta::wgpu::AddressMode::ClampToEdge,
-                mag_filter: vesta::wgpu::FilterMode::Nearest,
-                min_filter: vesta::wgpu::FilterMode::Nearest,
-                mipmap_filter: vesta::wgpu::FilterMode::Nearest,
-                ..Default::default()
-            });
-
-        // Create the appropriate bind group for the input data
-        let texture_bind_group =
-            renderer
-                .device
-                .create_bind_group(&vesta::wgpu::BindGroupDescriptor {
-                    layout: &vesta::Texture::create_bind_group_layout(&renderer.device),
-                    entries: &[
-                        vesta::wgpu::BindGroupEntry {
-                            binding: 0,
-                            resource: vesta::wgpu::BindingResource::TextureView(&view),
-                        },
-                        vesta::wgpu::BindGroupEntry {
-                            binding: 1,
-                            resource: vesta::wgpu::BindingResource::Sampler(&sampler),
-                        },
-                    ],
-                    label: Some("texture_bind_group"),
-                });
+            .create_dynamic_texture(
+                CHUNK_SIZE as u32,
+                CHUNK_SIZE as u32,
+                vesta::wgpu::TextureFormat::Rgba8UnormSrgb,
+                Some("Chunk Texture"),
+                vesta::TextureConfig {
+                    sampler_mag_filter: vesta::wgpu::FilterMode::Nearest,
+                    sampler_min_filter: vesta::wgpu::FilterMode::Nearest,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
 
         let data = vec![Pixel::default(); (CHUNK_SIZE * CHUNK_SIZE) as usize];
         let rng = rand::thread_rng();
@@ -117,23 +74,10 @@ impl Chunk {
             position,
             texture_mesh,
             texture,
-            texture_bind_group,
-            uniform_buffer,
+            uniform_slot,
             data,
-            color_buffer: Self::create_color_buffer(),
             loaded: false,
             rng,
-            dirty: false,
-        }
-    }
-
-    /// Helper function to create the color buffer
-    fn create_color_buffer() -> Box<[u8; CHUNK_SIZE as usize * CHUNK_SIZE as usize * 4]> {
-        let mut data = ManuallyDrop::new(vec![0; CHUNK_SIZE as usize * CHUNK_SIZE as usize * 4]);
-        unsafe {
-            Box::from_raw(
-                data.as_mut_ptr() as *mut [u8; CHUNK_SIZE as usize * CHUNK_SIZE as usize * 4]
-            )
         }
     }
 
@@ -145,10 +89,14 @@ impl Chunk {
         self.loaded = true;
     }
 
-    pub fn render<'a>(&'a self, render_pass: &mut vesta::wgpu::RenderPass<'a>) {
+    pub fn render<'a>(
+        &'a self,
+        render_pass: &mut vesta::wgpu::RenderPass<'a>,
+        uniforms: &'a vesta::UniformArena<vesta::ModelUniform>,
+    ) {
         if self.loaded {
-            render_pass.set_bind_group(1, &self.uniform_buffer.bind_group, &[]);
-            render_pass.set_bind_group(2, &self.texture_bind_group, &[]);
+            uniforms.bind(render_pass, 1, &self.uniform_slot);
+            render_pass.set_bind_group(2, self.texture.texture.bind_group.as_ref().unwrap(), &[]);
             render_pass.draw_mesh(&self.texture_mesh);
         }
     }
@@ -188,30 +136,6 @@ impl Chunk {
         }
     }
 
-    /// Write the raw data to the GPU via a texture
-    fn write_to_gpu(&mut self, renderer: &vesta::Renderer) {
-        // Write this buffer to the GPU
-        renderer.queue.write_texture(
-            vesta::wgpu::ImageCopyTexture {
-                texture: &self.texture,
-                mip_level: 0,
-                origin: vesta::wgpu::Origin3d::ZERO,
-                aspect: vesta::wgpu::TextureAspect::All,
-            },
-            self.color_buffer.as_mut(),
-            vesta::wgpu::ImageDataLayout {
-                offset: 0,
-                bytes_per_row: NonZeroU32::new((4 * CHUNK_SIZE) as u32),
-                rows_per_image: NonZeroU32::new(CHUNK_SIZE as u32),
-            },
-            vesta::wgpu::Extent3d {
-                width: CHUNK_SIZE as u32,
-                height: CHUNK_SIZE as u32,
-                depth_or_array_layers: 1,
-            },
-        );
-    }
-
     #[inline(always)]
     fn get_pixel_raw(&mut self, x: isize, y: isize) -> Option<&mut Pixel> {
         if Self::pixel_in_bounds(Vector2::new(x, y)) == false {
@@ -238,11 +162,9 @@ impl Chunk {
 
         self.data[Self::pixel_index(x, y)] = pixel;
 
-        pixel
-            .get_color()
-            .write_color_to_buffer(Self::pixel_index(x, y), &mut self.color_buffer);
-
-        self.dirty = true;
+        // Pixels are stored by column, so x is the row within the texture
+        self.texture
+            .set_pixel(y as u32, x as u32, &pixel.get_color().to_rgba());
     }
 
     fn create_model_matrix(position: Vector2<f32>) -> Matrix4<f32> {
@@ -261,10 +183,7 @@ impl Chunk {
     }
 
     pub fn rebuild(&mut self, renderer: &vesta::Renderer) {
-        if self.dirty {
-            self.write_to_gpu(renderer);
-            self.dirty = false;
-        }
+        self.texture.flush(&renderer.queue);
     }
 
     pub fn update(&mut self, engine: &vesta::Engine) {
@@ -11,6 +11,7 @@ fn main() {
     let config = vesta::Config {
         window_title: "Pixel 2D".to_string(),
         window_size: PhysicalSize::new(1920, 1080),
+        ..Default::default()
     };
 
     // Create for App, and pass in the config
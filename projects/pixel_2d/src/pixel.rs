@@ -1,8 +1,6 @@
 use rand::Rng;
 use vesta::cgmath::Vector2;
 
-use crate::chunk::CHUNK_SIZE;
-
 #[derive(Copy, Clone, Debug, Default)]
 pub struct Color {
     pub r: u8,
@@ -11,15 +9,8 @@ pub struct Color {
 }
 
 impl Color {
-    pub fn write_color_to_buffer(
-        &self,
-        i: usize,
-        buffer: &mut Box<[u8; CHUNK_SIZE as usize * CHUNK_SIZE as usize * 4]>,
-    ) {
-        buffer[(i * 4)] = self.r;
-        buffer[(i * 4) + 1] = self.g;
-        buffer[(i * 4) + 2] = self.b;
-        buffer[(i * 4) + 3] = 255;
+    pub fn to_rgba(&self) -> [u8; 4] {
+        [self.r, self.g, self.b, 255]
     }
 }
 
@@ -8,6 +8,7 @@ use crate::{
 
 pub struct World {
     chunks: Vec<Chunk>,
+    chunk_uniforms: vesta::UniformArena<vesta::ModelUniform>,
 }
 
 impl World {
@@ -15,15 +16,28 @@ impl World {
         let mut rng = rand::thread_rng();
         let seed = rng.gen();
 
+        let mut chunk_uniforms = renderer.create_uniform_arena(
+            "Chunk Uniform Arena",
+            vesta::wgpu::ShaderStages::VERTEX,
+            3,
+        );
+
         let mut chunks = Vec::new();
-        chunks.push(Chunk::new(&renderer, Vector2::new(0.0, 0.0), seed));
         chunks.push(Chunk::new(
             &renderer,
+            &mut chunk_uniforms,
+            Vector2::new(0.0, 0.0),
+            seed,
+        ));
+        chunks.push(Chunk::new(
+            &renderer,
+            &mut chunk_uniforms,
             Vector2::new(CHUNK_RENDER_SIZE * 1.0, 0.0),
             seed,
         ));
         chunks.push(Chunk::new(
             &renderer,
+            &mut chunk_uniforms,
             Vector2::new(CHUNK_RENDER_SIZE * -1.0, 0.0),
             seed,
         ));
@@ -34,12 +48,15 @@ impl World {
             c.rebuild(renderer);
         }
 
-        Self { chunks }
+        Self {
+            chunks,
+            chunk_uniforms,
+        }
     }
 
     pub fn draw<'a>(&'a self, render_pass: &mut vesta::wgpu::RenderPass<'a>) {
         for c in self.chunks.iter() {
-            c.render(render_pass);
+            c.render(render_pass, &self.chunk_uniforms);
         }
     }
 
@@ -58,8 +58,8 @@ impl vesta::VestaApp for App {
                             vesta::wgpu::ShaderStages::VERTEX | vesta::wgpu::ShaderStages::FRAGMENT,
                             &engine.renderer.device,
                         ),
-                        // Chunk Uniform buffer
-                        &vesta::UniformBufferUtils::create_bind_group_layout(
+                        // Chunk Uniform Arena
+                        &vesta::UniformBufferUtils::create_dynamic_bind_group_layout(
                             vesta::wgpu::ShaderStages::VERTEX,
                             &engine.renderer.device,
                         ),
@@ -70,39 +70,49 @@ impl vesta::VestaApp for App {
                 });
 
         // Render pipeline for shaders
-        let chunk_render_pipeline = vesta::RenderPipelineBuilder::new(
-            engine.renderer.surface_config.format,
-            "Chunk Render Pipeline",
-        )
-        .with_shader_source(vesta::wgpu::ShaderSource::Wgsl(
-            include_str!("res/chunk_shader.wgsl").into(),
-        ))
-        .with_layout(&render_pipeline_layout)
-        .build(&engine.renderer.device)
-        .unwrap();
-
-        let chunk_render_pipeline_wire_frame = vesta::RenderPipelineBuilder::new(
-            engine.renderer.surface_config.format,
+        let chunk_render_pipeline =
+            vesta::RenderPipelineBuilder::new(&engine.renderer, "Chunk Render Pipeline")
+                .with_shader_source(vesta::wgpu::ShaderSource::Wgsl(
+                    include_str!("res/chunk_shader.wgsl").into(),
+                ))
+                .with_layout(&render_pipeline_layout)
+                .build(&engine.renderer.device)
+                .unwrap();
+
+        // Draw a true wireframe when supported, otherwise fall back to drawing the indices as lines
+        let mut wire_frame_builder = vesta::RenderPipelineBuilder::new(
+            &engine.renderer,
             "Chunk Render Pipeline (Debug)",
-        )
-        .with_shader_source(vesta::wgpu::ShaderSource::Wgsl(
-            include_str!("res/chunk_shader.wgsl").into(),
-        ))
-        .with_layout(&render_pipeline_layout)
-        .with_topology(vesta::wgpu::PrimitiveTopology::LineList)
-        .build(&engine.renderer.device)
-        .unwrap();
-
-        let general_render_pipeline = vesta::RenderPipelineBuilder::new(
-            engine.renderer.surface_config.format,
-            "General Render Pipeline",
-        )
-        .with_shader_source(vesta::wgpu::ShaderSource::Wgsl(
-            include_str!("res/general_shader.wgsl").into(),
-        ))
-        .with_layout(&general_pipeline_layout)
-        .build(&engine.renderer.device)
-        .unwrap();
+        );
+        wire_frame_builder
+            .with_shader_source(vesta::wgpu::ShaderSource::Wgsl(
+                include_str!("res/chunk_shader.wgsl").into(),
+            ))
+            .with_layout(&render_pipeline_layout);
+
+        if engine
+            .renderer
+            .features()
+            .contains(vesta::wgpu::Features::POLYGON_MODE_LINE)
+        {
+            wire_frame_builder
+                .with_polygon_mode(vesta::wgpu::PolygonMode::Line)
+                .with_cull_mode(None);
+        } else {
+            wire_frame_builder.with_topology(vesta::wgpu::PrimitiveTopology::LineList);
+        }
+
+        let chunk_render_pipeline_wire_frame =
+            wire_frame_builder.build(&engine.renderer.device).unwrap();
+
+        let general_render_pipeline =
+            vesta::RenderPipelineBuilder::new(&engine.renderer, "General Render Pipeline")
+                .with_shader_source(vesta::wgpu::ShaderSource::Wgsl(
+                    include_str!("res/general_shader.wgsl").into(),
+                ))
+                .with_layout(&general_pipeline_layout)
+                .build(&engine.renderer.device)
+                .unwrap();
 
         // Setup the main camera
         let camera = vesta::CameraBuilder::new()
@@ -118,7 +128,7 @@ impl vesta::VestaApp for App {
                     0.01,
                     1000.0,
                 ),
-                &engine.renderer.device,
+                &engine.renderer,
             );
 
         let camera_controller = vesta::FpsCameraController::default();
@@ -194,7 +194,7 @@ impl Cube {
             "Cube Uniform Buffer",
             vesta::wgpu::ShaderStages::VERTEX,
             uniform_data,
-            &renderer.device,
+            renderer,
         );
 
         Self {
@@ -11,6 +11,14 @@ fn main() {
     let config = vesta::Config {
         window_title: "Project Titan".to_string(),
         window_size: (1920, 1080).into(),
+        sample_count: 4,
+        post_processing: true,
+        hdr: vesta::HdrConfig {
+            enabled: true,
+            tonemapper: vesta::Tonemapper::Aces,
+            ..Default::default()
+        },
+        ..Default::default()
     };
 
     // Create for App, and pass in the config
@@ -53,21 +53,19 @@ impl SkyShader {
                 });
 
         // Render pipeline for shaders
-        let render_pipeline = vesta::RenderPipelineBuilder::new(
-            engine.renderer.surface_config.format,
-            "Sky Render Pipeline",
-        )
-        .with_shader_source(vesta::wgpu::ShaderSource::Wgsl(
-            include_str!("res/sky_shader.wgsl").into(),
-        ))
-        .with_layout(&layout)
-        .with_vertex_buffer_layout(&[])
-        .with_depth_write_enabled(false)
-        .with_depth_compare(vesta::wgpu::CompareFunction::LessEqual)
-        .with_front_face(vesta::wgpu::FrontFace::Cw)
-        .with_cull_mode(None)
-        .build(&engine.renderer.device)
-        .unwrap();
+        let render_pipeline =
+            vesta::RenderPipelineBuilder::new(&engine.renderer, "Sky Render Pipeline")
+                .with_shader_source(vesta::wgpu::ShaderSource::Wgsl(
+                    include_str!("res/sky_shader.wgsl").into(),
+                ))
+                .with_layout(&layout)
+                .with_vertex_buffer_layout(&[])
+                .with_depth_write_enabled(false)
+                .with_depth_compare(vesta::wgpu::CompareFunction::LessEqual)
+                .with_front_face(vesta::wgpu::FrontFace::Cw)
+                .with_cull_mode(None)
+                .build(&engine.renderer.device)
+                .unwrap();
 
         // The uniform buffer
         let uniform_buffer = vesta::UniformBuffer::new(
@@ -79,7 +77,7 @@ impl SkyShader {
                 view: Matrix4::identity(),
                 cam_pos: Vector4::new(0.0, 0.0, 0.0, 0.0),
             },
-            &engine.renderer.device,
+            &engine.renderer,
         );
 
         // The uniform buffer
@@ -89,7 +87,7 @@ impl SkyShader {
             SkyFragUniform {
                 scatter_amount: 0.0,
             },
-            &engine.renderer.device,
+            &engine.renderer,
         );
 
         Self {
@@ -41,14 +41,18 @@ pub struct Chunk {
     /// 1D Array of all blocks in this chunk
     blocks: Vec<BlockType>,
 
-    /// Tells the GPU how to render the object
-    uniform_buffer: vesta::UniformBuffer<vesta::ModelUniform>,
+    /// Tells the GPU how to render the object, allocated in the world's chunk uniform arena
+    uniform_slot: vesta::UniformSlot,
 }
 
 impl Chunk {
     /// Create a new chunk, this only performs the bare minimum in order to maximise
     /// parallel processing later on
-    pub fn new(position: Vector3<f32>, renderer: &vesta::Renderer) -> Self {
+    pub fn new(
+        position: Vector3<f32>,
+        renderer: &vesta::Renderer,
+        uniforms: &mut vesta::UniformArena<vesta::ModelUniform>,
+    ) -> Self {
         let rotation: Quaternion<f32> = Quaternion::new(0.0, 0.0, 0.0, 0.0);
         let model = Matrix4::from_translation(position) * Matrix4::from(rotation);
         //let normal = Matrix3::from_cols(model.x.truncate(), model.y.truncate(), model.z.truncate());
@@ -60,13 +64,7 @@ impl Chunk {
             inverted_model.z.truncate(),
         );
 
-        let uniform_data = vesta::ModelUniform { model, normal };
-        let uniform_buffer = vesta::UniformBuffer::new(
-            "C-Body Uniform Buffer",
-            vesta::wgpu::ShaderStages::VERTEX,
-            uniform_data,
-            &renderer.device,
-        );
+        let uniform_slot = uniforms.allocate(&vesta::ModelUniform { model, normal }, renderer);
 
         Self {
             position,
@@ -74,7 +72,7 @@ impl Chunk {
             water_mesh: None,
             state: ChunkState::Created,
             blocks: vec![BlockType::Air; (CHUNK_WIDTH * CHUNK_WIDTH * CHUNK_HEIGHT) as usize],
-            uniform_buffer,
+            uniform_slot,
         }
     }
 
@@ -308,6 +306,7 @@ impl Chunk {
     pub fn render<'a>(
         &'a self,
         render_pass: &mut vesta::wgpu::RenderPass<'a>,
+        uniforms: &'a vesta::UniformArena<vesta::ModelUniform>,
         _engine: &vesta::Engine,
     ) {
         // Only render if there is a mesh and the chunk is in the correct state
@@ -323,13 +322,14 @@ impl Chunk {
             return;
         }
 
-        render_pass.set_bind_group(1, &self.uniform_buffer.bind_group, &[]);
+        uniforms.bind(render_pass, 1, &self.uniform_slot);
         render_pass.draw_mesh(self.mesh.as_ref().unwrap());
     }
 
     pub fn render_water<'a>(
         &'a self,
         render_pass: &mut vesta::wgpu::RenderPass<'a>,
+        uniforms: &'a vesta::UniformArena<vesta::ModelUniform>,
         _engine: &vesta::Engine,
     ) {
         // Only render if there is a mesh and the chunk is in the correct state
@@ -345,7 +345,7 @@ impl Chunk {
             return;
         }
 
-        render_pass.set_bind_group(1, &self.uniform_buffer.bind_group, &[]);
+        uniforms.bind(render_pass, 1, &self.uniform_slot);
         render_pass.draw_mesh(self.water_mesh.as_ref().unwrap());
     }
 
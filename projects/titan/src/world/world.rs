@@ -38,6 +38,8 @@ pub struct World {
     chunks: Vec<Chunk>,
     //chunk_map: HashMap<Vector3<i32>, Arc<Chunk>>,
     block_map_texture: vesta::Texture,
+    /// Model uniforms for every chunk, bound with each chunk's offset
+    chunk_uniforms: vesta::UniformArena<vesta::ModelUniform>,
     pub rendered_chunks: usize,
     generator: Generator,
 
@@ -60,11 +62,20 @@ impl World {
                     sampler_mag_filter: vesta::wgpu::FilterMode::Nearest,
                     sampler_min_filter: vesta::wgpu::FilterMode::Nearest,
                     sampler_mipmap_filter: vesta::wgpu::FilterMode::Nearest,
+                    generate_mipmaps: true,
+                    mipmap_atlas_tile_size: Some((16, 16)),
                     ..Default::default()
                 },
             )
             .unwrap();
 
+        // Enough slots for every chunk within the create distance
+        let chunk_uniforms = renderer.create_uniform_arena(
+            "Chunk Uniform Arena",
+            vesta::wgpu::ShaderStages::VERTEX,
+            (CREATE_DISTANCE * 2).pow(2),
+        );
+
         let generator = Generator::new(seed);
 
         let pool = lagoon::ThreadPool::default();
@@ -74,6 +85,7 @@ impl World {
         Self {
             chunks,
             block_map_texture,
+            chunk_uniforms,
             rendered_chunks: 0,
             generator,
             created_this_frame: 0,
@@ -123,8 +135,11 @@ impl World {
         for x in (center_x - create_distance..center_x + create_distance).step_by(step) {
             for z in (center_z - create_distance..center_z + create_distance).step_by(step) {
                 if !self.chunk_at(Vector3::new(x as f32, 0.0, z as f32)) {
-                    self.chunks
-                        .push(Chunk::new(Vector3::new(x as f32, 0.0, z as f32), &renderer));
+                    self.chunks.push(Chunk::new(
+                        Vector3::new(x as f32, 0.0, z as f32),
+                        &renderer,
+                        &mut self.chunk_uniforms,
+                    ));
                     self.created_this_frame += 1;
                 }
 
@@ -192,7 +207,7 @@ impl World {
                     chunk.get_position()
                         + Vector3::new(CHUNK_WIDTH as f32, CHUNK_HEIGHT as f32, CHUNK_WIDTH as f32),
                 ) {
-                    chunk.render(render_pass, engine);
+                    chunk.render(render_pass, &self.chunk_uniforms, engine);
                     self.rendered_chunks += 1;
                 }
             }
@@ -214,7 +229,7 @@ impl World {
                     chunk.get_position()
                         + Vector3::new(CHUNK_WIDTH as f32, CHUNK_HEIGHT as f32, CHUNK_WIDTH as f32),
                 ) {
-                    chunk.render_water(render_pass, engine);
+                    chunk.render_water(render_pass, &self.chunk_uniforms, engine);
                 }
             }
         }
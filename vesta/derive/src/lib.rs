@@ -0,0 +1,197 @@
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote};
+use syn::{
+    parse_macro_input, spanned::Spanned, Attribute, Data, DeriveInput, Error, Fields, Lit, Meta,
+    NestedMeta, Result,
+};
+
+/// Derive `vesta::VertexLayout` for a `#[repr(C)]` struct. Each field becomes one (or more for
+/// matrices) vertex attributes, with shader locations assigned in field order.
+///
+/// Struct attributes:
+/// - `#[vertex(instance)]` step the buffer per instance instead of per vertex
+/// - `#[vertex(start_location = 4)]` the shader location of the first attribute
+///
+/// Field attributes:
+/// - `#[vertex(location = 2)]` override the shader location (following fields continue from here)
+/// - `#[vertex(format = "Unorm8x4")]` override the inferred `wgpu::VertexFormat`
+/// - `#[vertex(skip)]` padding or CPU only data that is not exposed to the shader
+#[proc_macro_derive(VertexLayout, attributes(vertex))]
+pub fn derive_vertex_layout(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    match expand_vertex_layout(input) {
+        Ok(tokens) => tokens.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+#[derive(Default)]
+struct VertexOptions {
+    instance: bool,
+    start_location: Option<u32>,
+    location: Option<u32>,
+    format: Option<syn::Ident>,
+    skip: bool,
+}
+
+fn parse_options(attrs: &[Attribute]) -> Result<VertexOptions> {
+    let mut options = VertexOptions::default();
+
+    for attr in attrs.iter().filter(|a| a.path.is_ident("vertex")) {
+        let list = match attr.parse_meta()? {
+            Meta::List(list) => list,
+            meta => return Err(Error::new(meta.span(), "expected #[vertex(...)]")),
+        };
+
+        for nested in list.nested {
+            match nested {
+                NestedMeta::Meta(Meta::Path(path)) if path.is_ident("instance") => {
+                    options.instance = true;
+                }
+                NestedMeta::Meta(Meta::Path(path)) if path.is_ident("skip") => {
+                    options.skip = true;
+                }
+                NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.is_ident("location") => {
+                    options.location = Some(parse_u32(&nv.lit)?);
+                }
+                NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.is_ident("start_location") => {
+                    options.start_location = Some(parse_u32(&nv.lit)?);
+                }
+                NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.is_ident("format") => {
+                    options.format = match &nv.lit {
+                        Lit::Str(s) => Some(format_ident!("{}", s.value(), span = s.span())),
+                        lit => return Err(Error::new(lit.span(), "expected a format string")),
+                    };
+                }
+                other => return Err(Error::new(other.span(), "unknown vertex attribute")),
+            }
+        }
+    }
+
+    Ok(options)
+}
+
+fn parse_u32(lit: &Lit) -> Result<u32> {
+    match lit {
+        Lit::Int(int) => int.base10_parse(),
+        lit => Err(Error::new(lit.span(), "expected an integer")),
+    }
+}
+
+fn expand_vertex_layout(input: DeriveInput) -> Result<TokenStream2> {
+    let name = &input.ident;
+
+    // The attributes are calculated in a const, which cannot depend on generic parameters
+    if !input.generics.params.is_empty() {
+        return Err(Error::new(
+            input.generics.span(),
+            "VertexLayout cannot be derived for generic structs",
+        ));
+    }
+
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => fields.named.iter().collect::<Vec<_>>(),
+            Fields::Unnamed(fields) => fields.unnamed.iter().collect::<Vec<_>>(),
+            Fields::Unit => Vec::new(),
+        },
+        _ => {
+            return Err(Error::new(
+                input.span(),
+                "VertexLayout can only be derived for structs",
+            ))
+        }
+    };
+
+    let struct_options = parse_options(&input.attrs)?;
+    let start_location = struct_options.start_location.unwrap_or(0);
+    let step_mode = if struct_options.instance {
+        quote!(::vesta::wgpu::VertexStepMode::Instance)
+    } else {
+        quote!(::vesta::wgpu::VertexStepMode::Vertex)
+    };
+
+    let mut counts = Vec::new();
+    let mut blocks = Vec::new();
+
+    for field in fields {
+        let options = parse_options(&field.attrs)?;
+        let ty = &field.ty;
+
+        if options.skip {
+            blocks.push(quote! {
+                offset = ::vesta::align_vertex_offset(offset, ::std::mem::align_of::<#ty>() as u64);
+                offset += ::std::mem::size_of::<#ty>() as u64;
+            });
+            continue;
+        }
+
+        let formats = match &options.format {
+            Some(format) => {
+                counts.push(quote!(1));
+                quote!(&[::vesta::wgpu::VertexFormat::#format])
+            }
+            None => {
+                counts.push(quote!(<#ty as ::vesta::VertexAttributeFormat>::FORMATS.len()));
+                quote!(<#ty as ::vesta::VertexAttributeFormat>::FORMATS)
+            }
+        };
+
+        let set_location = options.location.map(|location| quote!(location = #location;));
+
+        blocks.push(quote! {
+            offset = ::vesta::align_vertex_offset(offset, ::std::mem::align_of::<#ty>() as u64);
+            #set_location
+            {
+                let formats: &[::vesta::wgpu::VertexFormat] = #formats;
+                let mut field_offset = offset;
+                let mut i = 0;
+                while i < formats.len() {
+                    attributes[index] = ::vesta::wgpu::VertexAttribute {
+                        format: formats[i],
+                        offset: field_offset,
+                        shader_location: location,
+                    };
+                    field_offset += formats[i].size();
+                    index += 1;
+                    location += 1;
+                    i += 1;
+                }
+            }
+            offset += ::std::mem::size_of::<#ty>() as u64;
+        });
+    }
+
+    Ok(quote! {
+        impl ::vesta::VertexLayout for #name {
+            fn layout<'a>() -> ::vesta::wgpu::VertexBufferLayout<'a> {
+                #[allow(clippy::identity_op)]
+                const COUNT: usize = 0 #(+ #counts)*;
+
+                #[allow(unused_mut, unused_variables, unused_assignments, clippy::identity_op)]
+                const ATTRIBUTES: [::vesta::wgpu::VertexAttribute; COUNT] = {
+                    let mut attributes = [::vesta::wgpu::VertexAttribute {
+                        format: ::vesta::wgpu::VertexFormat::Float32,
+                        offset: 0,
+                        shader_location: 0,
+                    }; COUNT];
+                    let mut index = 0;
+                    let mut offset: u64 = 0;
+                    let mut location: u32 = #start_location;
+
+                    #(#blocks)*
+
+                    attributes
+                };
+
+                ::vesta::wgpu::VertexBufferLayout {
+                    array_stride: ::std::mem::size_of::<#name>() as ::vesta::wgpu::BufferAddress,
+                    step_mode: #step_mode,
+                    attributes: &ATTRIBUTES,
+                }
+            }
+        }
+    })
+}
@@ -0,0 +1,210 @@
+use std::sync::Arc;
+
+use crate::Renderer;
+
+impl Renderer {
+    /// Get the bind group layout for these entries, it is only created (with this label) the
+    /// first time a layout with the same entries is requested
+    pub fn get_bind_group_layout(
+        &self,
+        label: &str,
+        entries: &[wgpu::BindGroupLayoutEntry],
+    ) -> Arc<wgpu::BindGroupLayout> {
+        let mut layouts = self
+            .bind_group_layouts
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        layouts
+            .entry(entries.to_vec())
+            .or_insert_with(|| {
+                Arc::new(
+                    self.device
+                        .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                            label: Some(label),
+                            entries,
+                        }),
+                )
+            })
+            .clone()
+    }
+}
+
+/// Builds a bind group with any combination of resources. Each added resource takes the next
+/// binding index, starting at 0, e.g.
+///
+/// ```ignore
+/// let (layout, bind_group) = vesta::BindGroupBuilder::new("Material")
+///     .uniform(&material_buffer, wgpu::ShaderStages::FRAGMENT)
+///     .texture(&texture.view, wgpu::ShaderStages::FRAGMENT)
+///     .sampler(&texture.sampler, wgpu::ShaderStages::FRAGMENT)
+///     .build(&renderer);
+/// ```
+pub struct BindGroupBuilder<'a> {
+    name: &'a str,
+    layout_entries: Vec<wgpu::BindGroupLayoutEntry>,
+    resources: Vec<wgpu::BindingResource<'a>>,
+}
+
+impl<'a> BindGroupBuilder<'a> {
+    pub fn new(name: &'a str) -> Self {
+        Self {
+            name,
+            layout_entries: Vec::new(),
+            resources: Vec::new(),
+        }
+    }
+
+    fn add(
+        &mut self,
+        ty: wgpu::BindingType,
+        visibility: wgpu::ShaderStages,
+        resource: wgpu::BindingResource<'a>,
+    ) -> &mut Self {
+        self.layout_entries.push(wgpu::BindGroupLayoutEntry {
+            binding: self.layout_entries.len() as u32,
+            visibility,
+            ty,
+            count: None,
+        });
+        self.resources.push(resource);
+        self
+    }
+
+    /// A whole buffer bound as `var<uniform>`
+    pub fn uniform(
+        &mut self,
+        buffer: &'a wgpu::Buffer,
+        visibility: wgpu::ShaderStages,
+    ) -> &mut Self {
+        self.add(
+            wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            visibility,
+            buffer.as_entire_binding(),
+        )
+    }
+
+    /// A whole buffer bound as `var<storage, read>` or `var<storage, read_write>`. Storage
+    /// buffers used in vertex shaders must be read only
+    pub fn storage(
+        &mut self,
+        buffer: &'a wgpu::Buffer,
+        read_only: bool,
+        visibility: wgpu::ShaderStages,
+    ) -> &mut Self {
+        self.add(
+            wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Storage { read_only },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            visibility,
+            buffer.as_entire_binding(),
+        )
+    }
+
+    /// A filterable float `texture_2d<f32>`
+    pub fn texture(
+        &mut self,
+        view: &'a wgpu::TextureView,
+        visibility: wgpu::ShaderStages,
+    ) -> &mut Self {
+        self.texture_with_type(
+            view,
+            wgpu::TextureViewDimension::D2,
+            wgpu::TextureSampleType::Float { filterable: true },
+            visibility,
+        )
+    }
+
+    /// A filterable float `texture_2d_array<f32>`, see `Texture::from_images_array`
+    pub fn texture_array(
+        &mut self,
+        view: &'a wgpu::TextureView,
+        visibility: wgpu::ShaderStages,
+    ) -> &mut Self {
+        self.texture_with_type(
+            view,
+            wgpu::TextureViewDimension::D2Array,
+            wgpu::TextureSampleType::Float { filterable: true },
+            visibility,
+        )
+    }
+
+    /// A texture of any dimension and sample type, e.g. a cube map or a depth texture
+    pub fn texture_with_type(
+        &mut self,
+        view: &'a wgpu::TextureView,
+        view_dimension: wgpu::TextureViewDimension,
+        sample_type: wgpu::TextureSampleType,
+        visibility: wgpu::ShaderStages,
+    ) -> &mut Self {
+        self.add(
+            wgpu::BindingType::Texture {
+                multisampled: false,
+                view_dimension,
+                sample_type,
+            },
+            visibility,
+            wgpu::BindingResource::TextureView(view),
+        )
+    }
+
+    /// A filtering sampler
+    pub fn sampler(
+        &mut self,
+        sampler: &'a wgpu::Sampler,
+        visibility: wgpu::ShaderStages,
+    ) -> &mut Self {
+        self.sampler_with_type(sampler, wgpu::SamplerBindingType::Filtering, visibility)
+    }
+
+    /// A sampler of any type, e.g. `SamplerBindingType::Comparison` for `sampler_comparison`
+    pub fn sampler_with_type(
+        &mut self,
+        sampler: &'a wgpu::Sampler,
+        ty: wgpu::SamplerBindingType,
+        visibility: wgpu::ShaderStages,
+    ) -> &mut Self {
+        self.add(
+            wgpu::BindingType::Sampler(ty),
+            visibility,
+            wgpu::BindingResource::Sampler(sampler),
+        )
+    }
+
+    /// The layout of the bind group, shared with every other bind group that has the same
+    /// entries. Use this when creating pipeline layouts
+    pub fn layout(&self, renderer: &Renderer) -> Arc<wgpu::BindGroupLayout> {
+        renderer.get_bind_group_layout(self.name, &self.layout_entries)
+    }
+
+    /// Create the bind group, returning it along with its layout
+    pub fn build(&self, renderer: &Renderer) -> (Arc<wgpu::BindGroupLayout>, wgpu::BindGroup) {
+        let layout = self.layout(renderer);
+
+        let entries: Vec<wgpu::BindGroupEntry> = self
+            .resources
+            .iter()
+            .enumerate()
+            .map(|(binding, resource)| wgpu::BindGroupEntry {
+                binding: binding as u32,
+                resource: resource.clone(),
+            })
+            .collect();
+
+        let bind_group = renderer
+            .device
+            .create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some(self.name),
+                layout: &layout,
+                entries: &entries,
+            });
+
+        (layout, bind_group)
+    }
+}
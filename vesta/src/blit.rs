@@ -0,0 +1,73 @@
+use crate::Texture;
+
+/// Copies a texture onto another texture of the same format by drawing it as a fullscreen
+/// triangle. Used to show frames that were rendered offscreen (e.g. for screenshots)
+pub(crate) struct Blitter {
+    render_pipeline: wgpu::RenderPipeline,
+}
+
+impl Blitter {
+    pub fn new(format: wgpu::TextureFormat, device: &wgpu::Device) -> Self {
+        let shader = device.create_shader_module(&wgpu::ShaderModuleDescriptor {
+            label: Some("Blit Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("shaders/blit.wgsl").into()),
+        });
+
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Blit Render Pipeline Layout"),
+            bind_group_layouts: &[&Texture::create_bind_group_layout(device)],
+            push_constant_ranges: &[],
+        });
+
+        // The render pipeline builder always uses depth, which a blit does not need
+        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Blit Render Pipeline"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::REPLACE),
+                    write_mask: wgpu::ColorWrites::ALL,
+                }],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        Self { render_pipeline }
+    }
+
+    /// Draw the source texture (which must have a bind group) over the whole target
+    pub fn blit(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        source: &Texture,
+        target: &wgpu::TextureView,
+    ) {
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Blit Render Pass"),
+            color_attachments: &[wgpu::RenderPassColorAttachment {
+                view: target,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: true,
+                },
+            }],
+            depth_stencil_attachment: None,
+        });
+
+        render_pass.set_pipeline(&self.render_pipeline);
+        render_pass.set_bind_group(0, source.bind_group.as_ref().unwrap(), &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+}
@@ -0,0 +1,216 @@
+use cgmath::{InnerSpace, Matrix4, Vector3};
+
+/// An axis aligned bounding box, described by its minimum and maximum corners
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Aabb {
+    pub min: Vector3<f32>,
+    pub max: Vector3<f32>,
+}
+
+impl Aabb {
+    pub fn new(min: Vector3<f32>, max: Vector3<f32>) -> Self {
+        Self { min, max }
+    }
+
+    /// Calculate the smallest box that contains all the provided points. Returns
+    /// None if there are no points
+    pub fn from_points<I: IntoIterator<Item = Vector3<f32>>>(points: I) -> Option<Self> {
+        let mut iter = points.into_iter();
+        let first = iter.next()?;
+
+        let mut aabb = Self::new(first, first);
+        for point in iter {
+            aabb.expand(point);
+        }
+
+        Some(aabb)
+    }
+
+    /// Grow this box so it contains the specified point
+    pub fn expand(&mut self, point: Vector3<f32>) {
+        self.min.x = self.min.x.min(point.x);
+        self.min.y = self.min.y.min(point.y);
+        self.min.z = self.min.z.min(point.z);
+
+        self.max.x = self.max.x.max(point.x);
+        self.max.y = self.max.y.max(point.y);
+        self.max.z = self.max.z.max(point.z);
+    }
+
+    /// Calculate a box that contains both this box and the other box
+    pub fn union(&self, other: &Aabb) -> Aabb {
+        let mut aabb = *self;
+        aabb.expand(other.min);
+        aabb.expand(other.max);
+        aabb
+    }
+
+    /// The center point of this box
+    pub fn center(&self) -> Vector3<f32> {
+        (self.min + self.max) * 0.5
+    }
+
+    /// The width, height and depth of this box
+    pub fn size(&self) -> Vector3<f32> {
+        self.max - self.min
+    }
+
+    /// Calculate the box that contains this box after it has been transformed by the specified
+    /// matrix (e.g. to move a mesh's local bounds into world space)
+    pub fn transform(&self, matrix: Matrix4<f32>) -> Aabb {
+        let corners = [
+            Vector3::new(self.min.x, self.min.y, self.min.z),
+            Vector3::new(self.max.x, self.min.y, self.min.z),
+            Vector3::new(self.min.x, self.max.y, self.min.z),
+            Vector3::new(self.max.x, self.max.y, self.min.z),
+            Vector3::new(self.min.x, self.min.y, self.max.z),
+            Vector3::new(self.max.x, self.min.y, self.max.z),
+            Vector3::new(self.min.x, self.max.y, self.max.z),
+            Vector3::new(self.max.x, self.max.y, self.max.z),
+        ];
+
+        Aabb::from_points(
+            corners
+                .iter()
+                .map(|corner| (matrix * corner.extend(1.0)).truncate()),
+        )
+        .unwrap()
+    }
+
+    /// Check if the ray intersects this box, returning the distance along the ray to the
+    /// entry point (or zero if the ray starts inside the box)
+    pub fn intersect_ray(&self, ray: &Ray) -> Option<f32> {
+        let mut t_min: f32 = 0.0;
+        let mut t_max = f32::INFINITY;
+
+        for axis in 0..3 {
+            let inv_direction = 1.0 / ray.direction[axis];
+            let mut t0 = (self.min[axis] - ray.origin[axis]) * inv_direction;
+            let mut t1 = (self.max[axis] - ray.origin[axis]) * inv_direction;
+
+            if inv_direction < 0.0 {
+                std::mem::swap(&mut t0, &mut t1);
+            }
+
+            // NaN comparisons are false, so rays parallel to a slab are handled correctly
+            t_min = if t0 > t_min { t0 } else { t_min };
+            t_max = if t1 < t_max { t1 } else { t_max };
+
+            if t_max < t_min {
+                return None;
+            }
+        }
+
+        Some(t_min)
+    }
+}
+
+/// A sphere that contains an object, cheaper to test against than a box
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BoundingSphere {
+    pub center: Vector3<f32>,
+    pub radius: f32,
+}
+
+impl BoundingSphere {
+    pub fn new(center: Vector3<f32>, radius: f32) -> Self {
+        Self { center, radius }
+    }
+
+    /// Calculate a sphere that contains all the provided points, centered on their bounding box.
+    /// Returns None if there are no points
+    pub fn from_points<I>(points: I) -> Option<Self>
+    where
+        I: IntoIterator<Item = Vector3<f32>>,
+        I::IntoIter: Clone,
+    {
+        let points = points.into_iter();
+        let center = Aabb::from_points(points.clone())?.center();
+
+        let radius_squared = points
+            .map(|point| (point - center).magnitude2())
+            .fold(0.0, f32::max);
+
+        Some(Self::new(center, radius_squared.sqrt()))
+    }
+}
+
+/// A ray with an origin and normalized direction, used for picking and collision
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Ray {
+    pub origin: Vector3<f32>,
+    pub direction: Vector3<f32>,
+}
+
+impl Ray {
+    /// Create a new ray, the direction is normalized
+    pub fn new(origin: Vector3<f32>, direction: Vector3<f32>) -> Self {
+        Self {
+            origin,
+            direction: direction.normalize(),
+        }
+    }
+
+    /// The point at the specified distance along this ray
+    pub fn at(&self, distance: f32) -> Vector3<f32> {
+        self.origin + self.direction * distance
+    }
+
+    /// Intersect this ray with a triangle (both sides), returning the distance along the
+    /// ray and the barycentric coordinates of the hit
+    pub fn intersect_triangle(
+        &self,
+        a: Vector3<f32>,
+        b: Vector3<f32>,
+        c: Vector3<f32>,
+    ) -> Option<(f32, f32, f32)> {
+        // Möller–Trumbore
+        let edge_1 = b - a;
+        let edge_2 = c - a;
+
+        // The determinant scales with the edge lengths, so the threshold does too. Otherwise
+        // small triangles miss and large near-degenerate ones hit
+        let p = self.direction.cross(edge_2);
+        let determinant = edge_1.dot(p);
+        if determinant.abs() < f32::EPSILON * edge_1.magnitude() * edge_2.magnitude() {
+            return None;
+        }
+
+        let inv_determinant = 1.0 / determinant;
+        let t = self.origin - a;
+
+        let u = t.dot(p) * inv_determinant;
+        if !(0.0..=1.0).contains(&u) {
+            return None;
+        }
+
+        let q = t.cross(edge_1);
+        let v = self.direction.dot(q) * inv_determinant;
+        if v < 0.0 || u + v > 1.0 {
+            return None;
+        }
+
+        let distance = edge_2.dot(q) * inv_determinant;
+        if distance < 0.0 {
+            return None;
+        }
+
+        Some((distance, u, v))
+    }
+}
+
+/// Information about where a ray hit a mesh
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Hit {
+    /// Distance along the ray to the hit
+    pub distance: f32,
+    /// The position of the hit, in the mesh's local space
+    pub point: Vector3<f32>,
+    /// The normal of the triangle that was hit, based on its winding order
+    pub normal: Vector3<f32>,
+    /// Index of the triangle that was hit (the first index of this triangle is `triangle * 3`)
+    pub triangle: u32,
+    /// Barycentric coordinates of the hit within the triangle (weights of the second and
+    /// third vertex)
+    pub barycentric: (f32, f32),
+}
@@ -0,0 +1,281 @@
+use cgmath::{InnerSpace, Vector3};
+
+use crate::{Aabb, Hit, Ray};
+
+/// A bounding volume hierarchy over the triangles of a mesh, used to speed up raycasts
+/// against large meshes
+pub struct Bvh {
+    nodes: Vec<BvhNode>,
+    // Triangle indices, ordered so every leaf references a contiguous range
+    triangles: Vec<u32>,
+}
+
+struct BvhNode {
+    bounds: Aabb,
+    // For leaves this is the first triangle, otherwise the index of the left child
+    // (the right child is always directly after it)
+    first: u32,
+    // Leaves have at least one triangle, nodes with zero triangles have children
+    count: u32,
+}
+
+impl Bvh {
+    /// Leaves are not split further once they have this many triangles
+    const MAX_LEAF_TRIANGLES: usize = 4;
+
+    /// Build a hierarchy over the triangles described by the positions and indices
+    pub fn new(positions: &[Vector3<f32>], indices: &[u32]) -> Self {
+        let triangle_count = indices.len() / 3;
+
+        let triangle_bounds: Vec<Aabb> = (0..triangle_count)
+            .map(|t| {
+                Aabb::from_points(
+                    indices[t * 3..t * 3 + 3]
+                        .iter()
+                        .map(|i| positions[*i as usize]),
+                )
+                .unwrap()
+            })
+            .collect();
+
+        let mut bvh = Self {
+            nodes: Vec::with_capacity(triangle_count * 2),
+            triangles: (0..triangle_count as u32).collect(),
+        };
+
+        if triangle_count > 0 {
+            bvh.nodes.push(BvhNode {
+                bounds: triangle_bounds[0],
+                first: 0,
+                count: triangle_count as u32,
+            });
+            bvh.subdivide(0, &triangle_bounds);
+        }
+
+        bvh
+    }
+
+    fn subdivide(&mut self, node_index: usize, triangle_bounds: &[Aabb]) {
+        let first = self.nodes[node_index].first as usize;
+        let count = self.nodes[node_index].count as usize;
+        let range = first..first + count;
+
+        // Calculate the bounds of this node, and the bounds of the triangle centers
+        // (which is used to pick the split axis)
+        let mut bounds = triangle_bounds[self.triangles[first] as usize];
+        let mut centers = Aabb::new(bounds.center(), bounds.center());
+        for triangle in &self.triangles[range.clone()] {
+            let triangle_bounds = triangle_bounds[*triangle as usize];
+            bounds = bounds.union(&triangle_bounds);
+            centers.expand(triangle_bounds.center());
+        }
+        self.nodes[node_index].bounds = bounds;
+
+        if count <= Self::MAX_LEAF_TRIANGLES {
+            return;
+        }
+
+        // Split along the longest axis, at the median triangle
+        let size = centers.size();
+        let axis = if size.x >= size.y && size.x >= size.z {
+            0
+        } else if size.y >= size.z {
+            1
+        } else {
+            2
+        };
+
+        self.triangles[range].sort_unstable_by(|a, b| {
+            let a = triangle_bounds[*a as usize].center()[axis];
+            let b = triangle_bounds[*b as usize].center()[axis];
+            a.partial_cmp(&b).unwrap_or(std::cmp::Ordering::Equal)
+        });
+
+        let left_count = count / 2;
+        let left_index = self.nodes.len();
+
+        self.nodes.push(BvhNode {
+            bounds,
+            first: first as u32,
+            count: left_count as u32,
+        });
+        self.nodes.push(BvhNode {
+            bounds,
+            first: (first + left_count) as u32,
+            count: (count - left_count) as u32,
+        });
+
+        self.nodes[node_index].first = left_index as u32;
+        self.nodes[node_index].count = 0;
+
+        self.subdivide(left_index, triangle_bounds);
+        self.subdivide(left_index + 1, triangle_bounds);
+    }
+
+    /// Find the closest triangle hit by the ray. The positions and indices must be the
+    /// same ones the hierarchy was built with
+    pub fn raycast(&self, ray: &Ray, positions: &[Vector3<f32>], indices: &[u32]) -> Option<Hit> {
+        let mut closest: Option<Hit> = None;
+        let mut stack = Vec::with_capacity(64);
+
+        if !self.nodes.is_empty() {
+            stack.push(0);
+        }
+
+        while let Some(node_index) = stack.pop() {
+            let node: &BvhNode = &self.nodes[node_index];
+
+            // Skip nodes that are missed, or are further away than the closest hit so far
+            match node.bounds.intersect_ray(ray) {
+                Some(distance) if !matches!(closest, Some(hit) if hit.distance < distance) => {}
+                _ => continue,
+            }
+
+            if node.count == 0 {
+                stack.push(node.first as usize);
+                stack.push(node.first as usize + 1);
+                continue;
+            }
+
+            let range = node.first as usize..(node.first + node.count) as usize;
+            for triangle in &self.triangles[range] {
+                if let Some(hit) = raycast_triangle(ray, positions, indices, *triangle) {
+                    if !matches!(closest, Some(closest) if closest.distance <= hit.distance) {
+                        closest = Some(hit);
+                    }
+                }
+            }
+        }
+
+        closest
+    }
+}
+
+/// Intersect a ray with every triangle, returning the closest hit. This is faster than
+/// building a hierarchy for small meshes
+pub(crate) fn raycast_triangles(
+    ray: &Ray,
+    positions: &[Vector3<f32>],
+    indices: &[u32],
+) -> Option<Hit> {
+    (0..(indices.len() / 3) as u32)
+        .filter_map(|triangle| raycast_triangle(ray, positions, indices, triangle))
+        .min_by(|a, b| {
+            a.distance
+                .partial_cmp(&b.distance)
+                .unwrap_or(std::cmp::Ordering::Equal)
+        })
+}
+
+fn raycast_triangle(
+    ray: &Ray,
+    positions: &[Vector3<f32>],
+    indices: &[u32],
+    triangle: u32,
+) -> Option<Hit> {
+    let i = triangle as usize * 3;
+    let a = positions[indices[i] as usize];
+    let b = positions[indices[i + 1] as usize];
+    let c = positions[indices[i + 2] as usize];
+
+    let (distance, u, v) = ray.intersect_triangle(a, b, c)?;
+
+    Some(Hit {
+        distance,
+        point: ray.at(distance),
+        normal: (b - a).cross(c - a).normalize(),
+        triangle,
+        barycentric: (u, v),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use cgmath::{vec3, InnerSpace, Vector3};
+
+    use super::Bvh;
+    use crate::Ray;
+
+    /// Quads facing +Z at each depth, spread along X so the BVH has to split them
+    fn quads(depths: &[f32], size: f32) -> (Vec<Vector3<f32>>, Vec<u32>) {
+        let mut positions = Vec::new();
+        let mut indices = Vec::new();
+
+        for (i, &z) in depths.iter().enumerate() {
+            let start = positions.len() as u32;
+            let x = i as f32 * 0.1 * size;
+            positions.extend([
+                vec3(x - size, -size, z),
+                vec3(x + size, -size, z),
+                vec3(x + size, size, z),
+                vec3(x - size, size, z),
+            ]);
+            indices.extend([start, start + 1, start + 2, start, start + 2, start + 3]);
+        }
+
+        (positions, indices)
+    }
+
+    #[test]
+    fn raycast_hits_triangle() {
+        let (positions, indices) = quads(&[-5.0], 1.0);
+        let bvh = Bvh::new(&positions, &indices);
+
+        let ray = Ray::new(vec3(0.0, 0.0, 0.0), vec3(0.0, 0.0, -1.0));
+        let hit = bvh.raycast(&ray, &positions, &indices).unwrap();
+
+        assert!((hit.distance - 5.0).abs() < 1e-5);
+        assert!((hit.point - vec3(0.0, 0.0, -5.0)).magnitude() < 1e-5);
+    }
+
+    #[test]
+    fn raycast_misses() {
+        let (positions, indices) = quads(&[-5.0], 1.0);
+        let bvh = Bvh::new(&positions, &indices);
+
+        // Pointing away, and passing beside the quad
+        let away = Ray::new(vec3(0.0, 0.0, 0.0), vec3(0.0, 0.0, 1.0));
+        let beside = Ray::new(vec3(3.0, 0.0, 0.0), vec3(0.0, 0.0, -1.0));
+
+        assert!(bvh.raycast(&away, &positions, &indices).is_none());
+        assert!(bvh.raycast(&beside, &positions, &indices).is_none());
+    }
+
+    #[test]
+    fn raycast_returns_closest_hit() {
+        // Out of order, so the closest hit is not the first triangle
+        let (positions, indices) = quads(&[-8.0, -2.0, -5.0, -12.0, -3.0], 1.0);
+        let bvh = Bvh::new(&positions, &indices);
+
+        let ray = Ray::new(vec3(0.2, 0.0, 0.0), vec3(0.0, 0.0, -1.0));
+        let hit = bvh.raycast(&ray, &positions, &indices).unwrap();
+
+        assert!((hit.distance - 2.0).abs() < 1e-5);
+        assert!(hit.triangle == 2 || hit.triangle == 3);
+    }
+
+    #[test]
+    fn raycast_hits_small_and_large_triangles() {
+        for size in [1e-5, 1e3] {
+            let (positions, indices) = quads(&[-5.0 * size], size);
+            let bvh = Bvh::new(&positions, &indices);
+
+            let ray = Ray::new(vec3(0.0, 0.0, 0.0), vec3(0.0, 0.0, -1.0));
+            let hit = bvh.raycast(&ray, &positions, &indices);
+
+            assert!(hit.is_some(), "missed a quad of size {}", size);
+        }
+    }
+
+    #[test]
+    fn intersect_triangle_rejects_parallel_ray() {
+        let ray = Ray::new(vec3(0.0, 0.0, 0.0), vec3(1.0, 0.0, 0.0));
+        let hit = ray.intersect_triangle(
+            vec3(-1.0, -1.0, -5.0),
+            vec3(1.0, -1.0, -5.0),
+            vec3(0.0, 1.0, -5.0),
+        );
+
+        assert!(hit.is_none());
+    }
+}
@@ -1,4 +1,4 @@
-use crate::{Camera, Projection};
+use crate::{Camera, Projection, Renderer};
 use cgmath::Vector3;
 
 pub struct CameraBuilder<'a> {
@@ -34,18 +34,14 @@ impl<'a> CameraBuilder<'a> {
         self
     }
 
-    /// Build a camera with a projection and required device
-    pub fn build(
-        &mut self,
-        projection: impl Projection + 'static,
-        device: &wgpu::Device,
-    ) -> Camera {
+    /// Build a camera with a projection, its uniform buffer is created on the renderer
+    pub fn build(&mut self, projection: impl Projection + 'static, renderer: &Renderer) -> Camera {
         Camera::new_internal(
             self.position,
             projection,
             self.uniform_buffer_visibility,
             self.uniform_buffer_name,
-            device,
+            renderer,
         )
     }
 }
@@ -9,7 +9,7 @@ pub use fps_camera_controller::*;
 use cgmath::num_traits::FloatConst;
 use cgmath::{EuclideanSpace, Matrix, Matrix4, Point3, Rad, SquareMatrix, Vector3, Vector4};
 
-use crate::{Projection, UniformBuffer};
+use crate::{Projection, Ray, Renderer, UniformBuffer};
 
 use crevice::std140::AsStd140;
 
@@ -43,7 +43,7 @@ impl Camera {
         projection: impl Projection + 'static,
         uniform_buffer_visibility: wgpu::ShaderStages,
         uniform_buffer_name: &str,
-        device: &wgpu::Device,
+        renderer: &Renderer,
     ) -> Self {
         // The uniform buffer
         let uniform_buffer = UniformBuffer::new(
@@ -53,7 +53,7 @@ impl Camera {
                 view_proj: Matrix4::identity(),
                 view_pos: Vector4::new(0.0, 0.0, 0.0, 0.0),
             },
-            device,
+            renderer,
         );
 
         Self {
@@ -117,4 +117,12 @@ impl Camera {
 
         Vector3::new(pos.x, pos.y, pos.z)
     }
+
+    /// Create a ray from the camera through the specified screen point, useful for picking
+    pub fn screen_to_ray(&self, x: f32, y: f32) -> Ray {
+        let near = self.screen_to_world_point(Vector3::new(x, y, 0.0));
+        let far = self.screen_to_world_point(Vector3::new(x, y, 1.0));
+
+        Ray::new(near, far - near)
+    }
 }
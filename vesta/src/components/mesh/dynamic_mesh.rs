@@ -0,0 +1,197 @@
+use std::marker::PhantomData;
+use std::ops::Range;
+
+use bevy_ecs::prelude::*;
+
+use crate::{Vertex, VertexLayout};
+
+use super::Mesh;
+
+/// A mesh that is expected to change often (e.g. voxel chunks or deforming terrain). The GPU
+/// buffers are kept around between updates and are only reallocated when the new geometry
+/// no longer fits
+#[derive(Component)]
+pub struct DynamicMesh<V: VertexLayout = Vertex> {
+    vertex_buffer: wgpu::Buffer,
+    index_buffer: wgpu::Buffer,
+    vertex_capacity: u32,
+    index_capacity: u32,
+    num_vertices: u32,
+    num_indices: u32,
+    draw_range: Option<Range<u32>>,
+    // Reused between updates so normals can be generated without allocating every time
+    scratch_vertices: Vec<Vertex>,
+    _vertex: PhantomData<fn() -> V>,
+}
+
+impl crate::Renderer {
+    /// Create a new dynamic mesh with space for the specified amount of vertices and indices
+    pub fn create_dynamic_mesh(&self, vertex_capacity: u32, index_capacity: u32) -> DynamicMesh {
+        DynamicMesh::new(vertex_capacity, index_capacity, &self.device)
+    }
+
+    /// Replace the contents of the dynamic mesh, growing the buffers if required
+    pub fn update_dynamic_mesh(
+        &self,
+        mesh: &mut DynamicMesh,
+        vertices: &[Vertex],
+        indices: &[u32],
+    ) {
+        mesh.update(vertices, indices, &self.device, &self.queue);
+    }
+}
+
+impl DynamicMesh {
+    /// Replace the contents of this mesh. The existing buffers are written to in place, unless
+    /// the new geometry is larger than the current capacity, in which case the buffers grow.
+    /// Smooth normals are generated, the same as a regular mesh
+    pub fn update(
+        &mut self,
+        vertices: &[Vertex],
+        indices: &[u32],
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) {
+        let mut scratch_vertices = std::mem::take(&mut self.scratch_vertices);
+        scratch_vertices.clear();
+        scratch_vertices.extend_from_slice(vertices);
+        Mesh::generate_normals(&mut scratch_vertices, indices);
+
+        self.update_raw(&scratch_vertices, indices, device, queue);
+        self.scratch_vertices = scratch_vertices;
+    }
+}
+
+impl<V: VertexLayout> DynamicMesh<V> {
+    /// When growing, buffers are allocated with this factor of headroom to avoid
+    /// reallocating again on the next small change
+    const GROWTH_FACTOR: u32 = 2;
+
+    pub fn new(vertex_capacity: u32, index_capacity: u32, device: &wgpu::Device) -> Self {
+        // Zero sized buffers cannot be bound, so always have space for at least one element
+        let vertex_capacity = vertex_capacity.max(1);
+        let index_capacity = index_capacity.max(1);
+
+        Self {
+            vertex_buffer: Self::create_vertex_buffer(vertex_capacity, device),
+            index_buffer: Self::create_index_buffer(index_capacity, device),
+            vertex_capacity,
+            index_capacity,
+            num_vertices: 0,
+            num_indices: 0,
+            draw_range: None,
+            scratch_vertices: Vec::new(),
+            _vertex: PhantomData,
+        }
+    }
+
+    /// Replace the contents of this mesh with any vertex type. The vertices are uploaded as is,
+    /// nothing (e.g. normals) is generated
+    pub fn update_raw(
+        &mut self,
+        vertices: &[V],
+        indices: &[u32],
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) {
+        let num_vertices = vertices.len() as u32;
+        let num_indices = indices.len() as u32;
+
+        if num_vertices > self.vertex_capacity {
+            self.vertex_capacity = Self::grow_capacity(self.vertex_capacity, num_vertices);
+            self.vertex_buffer = Self::create_vertex_buffer(self.vertex_capacity, device);
+        }
+
+        if num_indices > self.index_capacity {
+            self.index_capacity = Self::grow_capacity(self.index_capacity, num_indices);
+            self.index_buffer = Self::create_index_buffer(self.index_capacity, device);
+        }
+
+        if !vertices.is_empty() {
+            queue.write_buffer(&self.vertex_buffer, 0, bytemuck::cast_slice(vertices));
+        }
+
+        if !indices.is_empty() {
+            queue.write_buffer(&self.index_buffer, 0, bytemuck::cast_slice(indices));
+        }
+
+        self.num_vertices = num_vertices;
+        self.num_indices = num_indices;
+
+        // The previous range may not be valid for the new geometry
+        self.draw_range = None;
+    }
+
+    /// Only draw a subset of the mesh. This is a range of indices, or vertices if the mesh
+    /// has no indices. The range is clamped to the current contents of the mesh
+    pub fn set_draw_range(&mut self, range: Option<Range<u32>>) {
+        self.draw_range = range;
+    }
+
+    /// The range of indices (or vertices if there are no indices) that will be drawn
+    pub fn draw_range(&self) -> Range<u32> {
+        let count = if self.num_indices == 0 {
+            self.num_vertices
+        } else {
+            self.num_indices
+        };
+
+        match &self.draw_range {
+            Some(range) => range.start.min(count)..range.end.min(count),
+            None => 0..count,
+        }
+    }
+
+    pub fn vertex_buffer(&self) -> &wgpu::Buffer {
+        &self.vertex_buffer
+    }
+
+    pub fn index_buffer(&self) -> &wgpu::Buffer {
+        &self.index_buffer
+    }
+
+    pub fn num_vertices(&self) -> u32 {
+        self.num_vertices
+    }
+
+    pub fn num_indices(&self) -> u32 {
+        self.num_indices
+    }
+
+    /// The amount of vertices that can be stored before the vertex buffer must grow
+    pub fn vertex_capacity(&self) -> u32 {
+        self.vertex_capacity
+    }
+
+    /// The amount of indices that can be stored before the index buffer must grow
+    pub fn index_capacity(&self) -> u32 {
+        self.index_capacity
+    }
+
+    fn grow_capacity(current: u32, required: u32) -> u32 {
+        let mut capacity = current.max(1);
+        while capacity < required {
+            capacity = capacity.saturating_mul(Self::GROWTH_FACTOR);
+        }
+
+        capacity
+    }
+
+    fn create_vertex_buffer(capacity: u32, device: &wgpu::Device) -> wgpu::Buffer {
+        device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Dynamic Vertex Buffer"),
+            size: (capacity as usize * std::mem::size_of::<V>()) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        })
+    }
+
+    fn create_index_buffer(capacity: u32, device: &wgpu::Device) -> wgpu::Buffer {
+        device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Dynamic Index Buffer"),
+            size: (capacity as usize * std::mem::size_of::<u32>()) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::INDEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        })
+    }
+}
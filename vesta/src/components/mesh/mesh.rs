@@ -1,61 +1,111 @@
-use crate::cgmath::InnerSpace;
+use std::marker::PhantomData;
+
+use crate::cgmath::{InnerSpace, Vector3};
+use crate::{
+    Aabb, BoundingSphere, Bvh, Hit, InstanceBuffer, Ray, Vertex, VertexLayout, INSTANCE_BUFFER_SLOT,
+};
 use wgpu::util::DeviceExt;
 
-use super::Mesh;
+use super::{DynamicMesh, Mesh, RetainedGeometry};
 
 impl crate::Renderer {
     /// Create a new mesh
-    pub fn create_mesh(&self, vertices: Vec<crate::Vertex>, indices: Vec<u32>) -> Mesh {
+    pub fn create_mesh(&self, vertices: Vec<Vertex>, indices: Vec<u32>) -> Mesh {
         Mesh::new(vertices, indices, &self.device)
     }
+
+    /// Create a new mesh that keeps a CPU side copy of its geometry, so it can be raycast against
+    pub fn create_mesh_retained(&self, vertices: Vec<Vertex>, indices: Vec<u32>) -> Mesh {
+        Mesh::new_retained(vertices, indices, &self.device)
+    }
+
+    /// Create a new mesh using a custom vertex type
+    pub fn create_mesh_raw<V: VertexLayout>(&self, vertices: &[V], indices: &[u32]) -> Mesh<V> {
+        Mesh::new_raw(vertices, indices, &self.device)
+    }
 }
 
 impl Mesh {
-    pub fn new(vertices: Vec<crate::Vertex>, indices: Vec<u32>, device: &wgpu::Device) -> Self {
-        // We need this for rendering
-        let num_indices = indices.len() as u32;
-        let num_vertices = vertices.len() as u32;
+    /// Create a new mesh, smooth normals are generated for the vertices
+    pub fn new(mut vertices: Vec<Vertex>, indices: Vec<u32>, device: &wgpu::Device) -> Self {
+        Self::generate_normals(&mut vertices, &indices);
+
+        let mut mesh = Self::new_raw(&vertices, &indices, device);
 
-        let mut vertices_mut = vertices.to_vec();
+        // Positions are already available, so bounds are always calculated for default meshes
+        if let Some(bounds) = Aabb::from_points(vertices.iter().map(|v| v.position)) {
+            mesh.bounds = Some(bounds);
+            mesh.bounding_sphere = BoundingSphere::from_points(vertices.iter().map(|v| v.position));
+        }
+
+        mesh
+    }
 
-        // Generate smooth vertices
-        if num_vertices != 0 {
-            for vertex in vertices_mut.iter_mut() {
-                vertex.normal = cgmath::Vector3::new(0.0, 0.0, 0.0);
-            }
+    /// Create a new mesh that keeps a CPU side copy of its positions and indices, so it can be
+    /// raycast against
+    pub fn new_retained(vertices: Vec<Vertex>, indices: Vec<u32>, device: &wgpu::Device) -> Self {
+        let positions = vertices.iter().map(|v| v.position).collect();
 
-            let mut i = 0;
-            while i < indices.len() {
-                let a = indices[i] as usize;
-                let b = indices[i + 1] as usize;
-                let c = indices[i + 2] as usize;
+        let mut mesh = Self::new(vertices, indices.clone(), device);
+        mesh.retain_geometry(positions, indices);
+
+        mesh
+    }
+
+    /// Generate smooth normals for the provided vertices, based on the triangles they are part of
+    pub(crate) fn generate_normals(vertices: &mut [Vertex], indices: &[u32]) {
+        if vertices.is_empty() {
+            return;
+        }
+
+        for vertex in vertices.iter_mut() {
+            vertex.normal = cgmath::Vector3::new(0.0, 0.0, 0.0);
+        }
 
-                let p = (vertices_mut[b].position - vertices_mut[a].position)
-                    .cross(vertices_mut[c].position - vertices_mut[a].position);
+        let mut i = 0;
+        while i < indices.len() {
+            let a = indices[i] as usize;
+            let b = indices[i + 1] as usize;
+            let c = indices[i + 2] as usize;
 
-                vertices_mut[a].normal += p;
-                vertices_mut[b].normal += p;
-                vertices_mut[c].normal += p;
+            let p = (vertices[b].position - vertices[a].position)
+                .cross(vertices[c].position - vertices[a].position);
 
-                i += 3;
-            }
+            vertices[a].normal += p;
+            vertices[b].normal += p;
+            vertices[c].normal += p;
 
-            for vertex in vertices_mut.iter_mut() {
-                vertex.normal = vertex.normal.normalize();
-            }
+            i += 3;
         }
 
+        for vertex in vertices.iter_mut() {
+            vertex.normal = vertex.normal.normalize();
+        }
+    }
+}
+
+impl<V: VertexLayout> Mesh<V> {
+    /// Meshes with more triangles than this build a hierarchy to speed up raycasts
+    const BVH_TRIANGLE_THRESHOLD: usize = 64;
+
+    /// Create a new mesh from any vertex type. The vertices are uploaded as is, nothing
+    /// (e.g. normals) is generated
+    pub fn new_raw(vertices: &[V], indices: &[u32], device: &wgpu::Device) -> Self {
+        // We need this for rendering
+        let num_indices = indices.len() as u32;
+        let num_vertices = vertices.len() as u32;
+
         // Create a vertex buffer using the vertices
         let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: Some("Vertex Buffer"),
-            contents: bytemuck::cast_slice(vertices_mut.as_slice()),
+            contents: bytemuck::cast_slice(vertices),
             usage: wgpu::BufferUsages::VERTEX,
         });
 
         // Create an index buffer using the indices
         let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: Some("Index Buffer"),
-            contents: bytemuck::cast_slice(indices.as_slice()),
+            contents: bytemuck::cast_slice(indices),
             usage: wgpu::BufferUsages::INDEX,
         });
 
@@ -64,6 +114,85 @@ impl Mesh {
             index_buffer,
             num_indices,
             num_vertices,
+            bounds: None,
+            bounding_sphere: None,
+            geometry: None,
+            _vertex: PhantomData,
+        }
+    }
+
+    /// Keep a CPU side copy of this mesh's geometry, this enables raycasts and recalculates
+    /// the bounds. If there are no indices, every three positions form a triangle
+    pub fn retain_geometry(&mut self, positions: Vec<Vector3<f32>>, indices: Vec<u32>) {
+        let indices = if indices.is_empty() {
+            (0..positions.len() as u32).collect()
+        } else {
+            indices
+        };
+
+        self.bounds = Aabb::from_points(positions.iter().copied());
+        self.bounding_sphere = BoundingSphere::from_points(positions.iter().copied());
+
+        // A hierarchy is only worth building once there are enough triangles
+        let bvh = if indices.len() / 3 > Self::BVH_TRIANGLE_THRESHOLD {
+            Some(Bvh::new(&positions, &indices))
+        } else {
+            None
+        };
+
+        self.geometry = Some(RetainedGeometry {
+            positions,
+            indices,
+            bvh,
+        });
+    }
+
+    /// Remove the CPU side copy of this mesh's geometry (the bounds are kept)
+    pub fn release_geometry(&mut self) {
+        self.geometry = None;
+    }
+
+    /// Manually set the bounds of this mesh, useful for custom vertex types where the
+    /// positions are not known
+    pub fn set_bounds(&mut self, bounds: Aabb) {
+        self.bounds = Some(bounds);
+        self.bounding_sphere = Some(BoundingSphere::new(
+            bounds.center(),
+            bounds.size().magnitude() * 0.5,
+        ));
+    }
+
+    /// The local space bounding box of this mesh
+    pub fn bounds(&self) -> Option<Aabb> {
+        self.bounds
+    }
+
+    /// The local space bounding sphere of this mesh
+    pub fn bounding_sphere(&self) -> Option<BoundingSphere> {
+        self.bounding_sphere
+    }
+
+    /// The retained CPU side positions of this mesh
+    pub fn positions(&self) -> Option<&[Vector3<f32>]> {
+        self.geometry.as_ref().map(|g| g.positions.as_slice())
+    }
+
+    /// The retained CPU side indices of this mesh
+    pub fn indices(&self) -> Option<&[u32]> {
+        self.geometry.as_ref().map(|g| g.indices.as_slice())
+    }
+
+    /// Find the closest point where the ray (in the mesh's local space) hits this mesh. This
+    /// requires the geometry to be retained, otherwise None is always returned
+    pub fn raycast(&self, ray: &Ray) -> Option<Hit> {
+        let geometry = self.geometry.as_ref()?;
+
+        // Quickly skip rays that miss the mesh entirely
+        self.bounds?.intersect_ray(ray)?;
+
+        match &geometry.bvh {
+            Some(bvh) => bvh.raycast(ray, &geometry.positions, &geometry.indices),
+            None => crate::bvh::raycast_triangles(ray, &geometry.positions, &geometry.indices),
         }
     }
 }
@@ -72,14 +201,20 @@ pub trait DrawMesh<'a, 'b>
 where
     'b: 'a,
 {
-    fn draw_mesh(&mut self, mesh: &'b Mesh);
+    fn draw_mesh<V: VertexLayout>(&mut self, mesh: &'b Mesh<V>);
+    fn draw_dynamic_mesh<V: VertexLayout>(&mut self, mesh: &'b DynamicMesh<V>);
+    fn draw_mesh_instanced<V: VertexLayout, T: bytemuck::Pod>(
+        &mut self,
+        mesh: &'b Mesh<V>,
+        instance_buffer: &'b InstanceBuffer<T>,
+    );
 }
 
 impl<'a, 'b> DrawMesh<'a, 'b> for wgpu::RenderPass<'a>
 where
     'b: 'a,
 {
-    fn draw_mesh(&mut self, mesh: &'b Mesh) {
+    fn draw_mesh<V: VertexLayout>(&mut self, mesh: &'b Mesh<V>) {
         self.set_vertex_buffer(0, mesh.vertex_buffer.slice(..));
 
         if mesh.num_indices == 0 {
@@ -89,4 +224,42 @@ where
             self.draw_indexed(0..mesh.num_indices, 0, 0..1);
         }
     }
+
+    fn draw_dynamic_mesh<V: VertexLayout>(&mut self, mesh: &'b DynamicMesh<V>) {
+        let range = mesh.draw_range();
+        if range.is_empty() {
+            return;
+        }
+
+        self.set_vertex_buffer(0, mesh.vertex_buffer().slice(..));
+
+        if mesh.num_indices() == 0 {
+            self.draw(range, 0..1);
+        } else {
+            self.set_index_buffer(mesh.index_buffer().slice(..), wgpu::IndexFormat::Uint32);
+            self.draw_indexed(range, 0, 0..1);
+        }
+    }
+
+    /// Draw every instance in the instance buffer using a single draw call. The instance
+    /// buffer is bound to `INSTANCE_BUFFER_SLOT`
+    fn draw_mesh_instanced<V: VertexLayout, T: bytemuck::Pod>(
+        &mut self,
+        mesh: &'b Mesh<V>,
+        instance_buffer: &'b InstanceBuffer<T>,
+    ) {
+        if instance_buffer.count() == 0 {
+            return;
+        }
+
+        self.set_vertex_buffer(0, mesh.vertex_buffer.slice(..));
+        self.set_vertex_buffer(INSTANCE_BUFFER_SLOT, instance_buffer.buffer().slice(..));
+
+        if mesh.num_indices == 0 {
+            self.draw(0..mesh.num_vertices, 0..instance_buffer.count());
+        } else {
+            self.set_index_buffer(mesh.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+            self.draw_indexed(0..mesh.num_indices, 0, 0..instance_buffer.count());
+        }
+    }
 }
@@ -0,0 +1,372 @@
+use std::collections::HashMap;
+
+use cgmath::{InnerSpace, Matrix, Matrix3, Matrix4, SquareMatrix};
+
+use crate::{Aabb, Vertex};
+
+use super::Mesh;
+
+/// CPU side mesh data, this can be uploaded to the GPU as a mesh, or appended
+/// to other mesh builders
+#[derive(Clone, Debug, Default)]
+pub struct MeshData {
+    pub vertices: Vec<Vertex>,
+    pub indices: Vec<u32>,
+}
+
+impl MeshData {
+    /// Calculate the bounding box of this mesh data. Returns None if there are no vertices
+    pub fn bounds(&self) -> Option<Aabb> {
+        Aabb::from_points(self.vertices.iter().map(|v| v.position))
+    }
+
+    /// Transform all the vertices in this mesh data by the specified matrix
+    pub fn transform(&mut self, matrix: Matrix4<f32>) {
+        let normal_matrix = Self::calculate_normal_matrix(matrix);
+
+        for vertex in self.vertices.iter_mut() {
+            vertex.position = (matrix * vertex.position.extend(1.0)).truncate();
+
+            // Zero length normals have not been generated yet, so there is nothing to transform
+            if vertex.normal.magnitude2() > 0.0 {
+                vertex.normal = (normal_matrix * vertex.normal).normalize();
+            }
+        }
+    }
+
+    /// Normals must be transformed by the inverse transpose of the model matrix, otherwise
+    /// non-uniform scaling will skew them
+    fn calculate_normal_matrix(matrix: Matrix4<f32>) -> Matrix3<f32> {
+        let upper = Matrix3::from_cols(
+            matrix.x.truncate(),
+            matrix.y.truncate(),
+            matrix.z.truncate(),
+        );
+
+        match upper.invert() {
+            Some(inverted) => inverted.transpose(),
+            None => upper,
+        }
+    }
+}
+
+/// Helper for building procedural geometry. Tracks the current vertex index so callers
+/// don't have to, and can optionally weld vertices that are (almost) identical
+pub struct MeshBuilder {
+    vertices: Vec<Vertex>,
+    indices: Vec<u32>,
+    weld_tolerance: Option<f32>,
+    /// Welded vertices, grouped by the grid cell their position falls in
+    weld_lookup: HashMap<[i32; 3], Vec<u32>>,
+}
+
+impl MeshBuilder {
+    pub fn new() -> Self {
+        Self {
+            vertices: Vec::new(),
+            indices: Vec::new(),
+            weld_tolerance: None,
+            weld_lookup: HashMap::new(),
+        }
+    }
+
+    /// Create a builder with pre-allocated space for the specified amount of vertices and indices
+    pub fn with_capacity(vertices: usize, indices: usize) -> Self {
+        Self {
+            vertices: Vec::with_capacity(vertices),
+            indices: Vec::with_capacity(indices),
+            ..Self::new()
+        }
+    }
+
+    /// Deduplicate vertices as they are pushed. A vertex whose attributes are all within the
+    /// specified tolerance of an existing vertex will reuse that vertex's index. A tolerance of
+    /// zero only merges exact duplicates
+    pub fn with_welding(&mut self, tolerance: f32) -> &mut Self {
+        let tolerance = tolerance.max(0.0);
+        self.weld_tolerance = Some(tolerance);
+
+        // Existing vertices must be known in order to weld against them
+        self.weld_lookup.clear();
+        for i in 0..self.vertices.len() {
+            let vertex = self.vertices[i];
+            if self.find_weld(&vertex, tolerance).is_none() {
+                self.weld_lookup
+                    .entry(Self::weld_cell(&vertex, tolerance))
+                    .or_default()
+                    .push(i as u32);
+            }
+        }
+
+        self
+    }
+
+    /// Push a vertex into the builder, returning its index
+    pub fn push_vertex(&mut self, vertex: Vertex) -> u32 {
+        if let Some(tolerance) = self.weld_tolerance {
+            if let Some(index) = self.find_weld(&vertex, tolerance) {
+                return index;
+            }
+
+            let index = self.vertices.len() as u32;
+            self.weld_lookup
+                .entry(Self::weld_cell(&vertex, tolerance))
+                .or_default()
+                .push(index);
+            self.vertices.push(vertex);
+            return index;
+        }
+
+        self.vertices.push(vertex);
+        (self.vertices.len() - 1) as u32
+    }
+
+    /// Push a triangle made up of three existing vertex indices (counter-clockwise)
+    pub fn push_triangle(&mut self, a: u32, b: u32, c: u32) {
+        self.indices.push(a);
+        self.indices.push(b);
+        self.indices.push(c);
+    }
+
+    /// Push a quad made up of four existing vertex indices (counter-clockwise), this
+    /// is split into two triangles
+    pub fn push_quad(&mut self, a: u32, b: u32, c: u32, d: u32) {
+        self.push_triangle(a, b, d);
+        self.push_triangle(b, c, d);
+    }
+
+    /// Push four vertices (counter-clockwise) and the quad that joins them
+    pub fn push_face(&mut self, vertices: [Vertex; 4]) {
+        let a = self.push_vertex(vertices[0]);
+        let b = self.push_vertex(vertices[1]);
+        let c = self.push_vertex(vertices[2]);
+        let d = self.push_vertex(vertices[3]);
+
+        self.push_quad(a, b, c, d);
+    }
+
+    /// Append existing mesh data to this builder, the indices are offset automatically
+    pub fn append(&mut self, data: &MeshData) {
+        let remap: Vec<u32> = data
+            .vertices
+            .iter()
+            .map(|vertex| self.push_vertex(*vertex))
+            .collect();
+
+        self.indices
+            .extend(data.indices.iter().map(|i| remap[*i as usize]));
+    }
+
+    /// Append existing mesh data to this builder after transforming it by the specified matrix
+    pub fn append_transformed(&mut self, data: &MeshData, matrix: Matrix4<f32>) {
+        let mut transformed = data.clone();
+        transformed.transform(matrix);
+
+        self.append(&transformed);
+    }
+
+    /// Transform all vertices currently in the builder by the specified matrix
+    pub fn transform(&mut self, matrix: Matrix4<f32>) {
+        let mut data = MeshData {
+            vertices: std::mem::take(&mut self.vertices),
+            indices: Vec::new(),
+        };
+        data.transform(matrix);
+
+        self.vertices = data.vertices;
+
+        // Positions have changed, so the weld lookup is no longer valid
+        if let Some(tolerance) = self.weld_tolerance {
+            self.with_welding(tolerance);
+        }
+    }
+
+    /// Calculate the bounding box of the vertices in this builder
+    pub fn bounds(&self) -> Option<Aabb> {
+        Aabb::from_points(self.vertices.iter().map(|v| v.position))
+    }
+
+    pub fn vertex_count(&self) -> usize {
+        self.vertices.len()
+    }
+
+    pub fn index_count(&self) -> usize {
+        self.indices.len()
+    }
+
+    /// Take the built data out of the builder, leaving it empty
+    pub fn build_data(&mut self) -> MeshData {
+        self.weld_lookup.clear();
+
+        MeshData {
+            vertices: std::mem::take(&mut self.vertices),
+            indices: std::mem::take(&mut self.indices),
+        }
+    }
+
+    /// Upload the built data to the GPU as a mesh, leaving the builder empty
+    pub fn build(&mut self, device: &wgpu::Device) -> Mesh {
+        let data = self.build_data();
+        Mesh::new(data.vertices, data.indices, device)
+    }
+
+    /// Upload the built data to the GPU as a mesh that keeps a CPU side copy of its
+    /// geometry (for raycasts), leaving the builder empty
+    pub fn build_retained(&mut self, device: &wgpu::Device) -> Mesh {
+        let data = self.build_data();
+        Mesh::new_retained(data.vertices, data.indices, device)
+    }
+
+    /// The index of a welded vertex within the tolerance of this one. The grid cells are the
+    /// size of the tolerance, so any match is in the same or a neighbouring cell
+    fn find_weld(&self, vertex: &Vertex, tolerance: f32) -> Option<u32> {
+        let cell = Self::weld_cell(vertex, tolerance);
+        let range = if tolerance > 0.0 { -1..=1 } else { 0..=0 };
+
+        for x in range.clone() {
+            for y in range.clone() {
+                for z in range.clone() {
+                    let neighbour = [
+                        cell[0].wrapping_add(x),
+                        cell[1].wrapping_add(y),
+                        cell[2].wrapping_add(z),
+                    ];
+                    let found = self.weld_lookup.get(&neighbour).and_then(|indices| {
+                        indices.iter().copied().find(|index| {
+                            Self::within_tolerance(
+                                &self.vertices[*index as usize],
+                                vertex,
+                                tolerance,
+                            )
+                        })
+                    });
+
+                    if found.is_some() {
+                        return found;
+                    }
+                }
+            }
+        }
+
+        None
+    }
+
+    /// The grid cell of the vertex position. With a tolerance of zero each distinct position
+    /// has its own cell
+    fn weld_cell(vertex: &Vertex, tolerance: f32) -> [i32; 3] {
+        let quantize = |value: f32| -> i32 {
+            if tolerance > 0.0 {
+                (value / tolerance).floor() as i32
+            } else {
+                // Make sure -0.0 and 0.0 are treated as the same value
+                (value + 0.0).to_bits() as i32
+            }
+        };
+
+        [
+            quantize(vertex.position.x),
+            quantize(vertex.position.y),
+            quantize(vertex.position.z),
+        ]
+    }
+
+    fn within_tolerance(a: &Vertex, b: &Vertex, tolerance: f32) -> bool {
+        let attributes = |vertex: &Vertex| {
+            [
+                vertex.position.x,
+                vertex.position.y,
+                vertex.position.z,
+                vertex.color.x,
+                vertex.color.y,
+                vertex.color.z,
+                vertex.tex_coord.x,
+                vertex.tex_coord.y,
+                vertex.normal.x,
+                vertex.normal.y,
+                vertex.normal.z,
+            ]
+        };
+
+        attributes(a)
+            .iter()
+            .zip(attributes(b).iter())
+            .all(|(a, b)| (a - b).abs() <= tolerance)
+    }
+}
+
+impl Default for MeshBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl crate::Renderer {
+    /// Create a new mesh from CPU side mesh data
+    pub fn create_mesh_from_data(&self, data: MeshData) -> Mesh {
+        Mesh::new(data.vertices, data.indices, &self.device)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cgmath::vec3;
+
+    use super::MeshBuilder;
+    use crate::Vertex;
+
+    fn vertex(x: f32) -> Vertex {
+        Vertex::with_color(vec3(x, 0.0, 0.0), vec3(1.0, 1.0, 1.0))
+    }
+
+    #[test]
+    fn welds_exact_duplicates() {
+        let mut builder = MeshBuilder::new();
+        builder.with_welding(0.0);
+
+        let a = builder.push_vertex(vertex(1.0));
+        let b = builder.push_vertex(vertex(1.0));
+        let c = builder.push_vertex(vertex(1.0 + 1e-6));
+
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+        assert_eq!(builder.vertex_count(), 2);
+    }
+
+    #[test]
+    fn welds_across_cell_boundaries() {
+        // Either side of a multiple (and a half multiple) of the tolerance, but well within it
+        for (a, b) in [(0.00099, 0.00101), (0.00149, 0.00151)] {
+            let mut builder = MeshBuilder::new();
+            builder.with_welding(0.001);
+
+            assert_eq!(
+                builder.push_vertex(vertex(a)),
+                builder.push_vertex(vertex(b))
+            );
+        }
+    }
+
+    #[test]
+    fn does_not_weld_beyond_tolerance() {
+        let mut builder = MeshBuilder::new();
+        builder.with_welding(0.001);
+
+        // In neighbouring cells, but further apart than the tolerance
+        let a = builder.push_vertex(vertex(0.0015));
+        let b = builder.push_vertex(vertex(0.0019));
+        let c = builder.push_vertex(vertex(0.0026));
+
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+    }
+
+    #[test]
+    fn welds_existing_vertices() {
+        let mut builder = MeshBuilder::new();
+        builder.push_vertex(vertex(1.0));
+        builder.push_vertex(vertex(1.0));
+        builder.with_welding(0.0);
+
+        assert_eq!(builder.push_vertex(vertex(1.0)), 0);
+    }
+}
@@ -1,15 +1,36 @@
+use std::marker::PhantomData;
+
 use bevy_ecs::prelude::*;
 
+use cgmath::Vector3;
+
+use crate::{Aabb, BoundingSphere, Bvh, Vertex, VertexLayout};
+
 pub mod cube;
+pub mod dynamic_mesh;
 pub mod mesh;
+pub mod mesh_builder;
 
 pub use cube::*;
+pub use dynamic_mesh::*;
 pub use mesh::*;
+pub use mesh_builder::*;
 
 #[derive(Component)]
-pub struct Mesh {
+pub struct Mesh<V: VertexLayout = Vertex> {
     vertex_buffer: wgpu::Buffer,
     index_buffer: wgpu::Buffer,
     num_indices: u32,
     num_vertices: u32,
+    bounds: Option<Aabb>,
+    bounding_sphere: Option<BoundingSphere>,
+    geometry: Option<RetainedGeometry>,
+    _vertex: PhantomData<fn() -> V>,
+}
+
+/// A CPU side copy of a mesh's triangles, kept around for raycasts and collision
+struct RetainedGeometry {
+    positions: Vec<Vector3<f32>>,
+    indices: Vec<u32>,
+    bvh: Option<Bvh>,
 }
@@ -0,0 +1,109 @@
+use anyhow::*;
+
+use crate::Renderer;
+
+pub struct ComputePipelineBuilder<'a> {
+    layout: Option<&'a wgpu::PipelineLayout>,
+    shader_source: Option<wgpu::ShaderModuleDescriptor<'a>>,
+    entry_point: &'a str,
+    pipeline_name: &'a str,
+}
+
+impl<'a> ComputePipelineBuilder<'a> {
+    pub fn new(pipeline_name: &'a str) -> ComputePipelineBuilder<'a> {
+        Self {
+            layout: None,
+            shader_source: None,
+            entry_point: "cs_main",
+            pipeline_name,
+        }
+    }
+
+    /// Set the pipeline layout. Without one, the layout is derived from the shader and the
+    /// bind group layouts can be fetched with `ComputePipeline::get_bind_group_layout`
+    pub fn with_layout(&mut self, layout: &'a wgpu::PipelineLayout) -> &mut Self {
+        self.layout = Some(layout);
+        self
+    }
+
+    pub fn with_shader_source(&mut self, source: wgpu::ShaderSource<'a>) -> &mut Self {
+        self.shader_source = Some(wgpu::ShaderModuleDescriptor {
+            label: None,
+            source,
+        });
+        self
+    }
+
+    /// The compute shader entry point, defaults to `cs_main`
+    pub fn with_entry_point(&mut self, entry_point: &'a str) -> &mut Self {
+        self.entry_point = entry_point;
+        self
+    }
+
+    pub fn build(&mut self, device: &wgpu::Device) -> Result<wgpu::ComputePipeline> {
+        let shader_module = device.create_shader_module(
+            &self
+                .shader_source
+                .take()
+                .context("No shader source supplied!")?,
+        );
+
+        Ok(
+            device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some(self.pipeline_name),
+                layout: self.layout,
+                module: &shader_module,
+                entry_point: self.entry_point,
+            }),
+        )
+    }
+}
+
+/// The number of workgroups needed to cover `size` invocations, when each workgroup has
+/// `workgroup_size` invocations
+pub fn workgroup_count(size: u32, workgroup_size: u32) -> u32 {
+    match size % workgroup_size {
+        0 => size / workgroup_size,
+        _ => size / workgroup_size + 1,
+    }
+}
+
+impl Renderer {
+    /// Run a compute pipeline straight away, in its own command buffer. Bind groups are set in
+    /// order starting at group 0
+    pub fn dispatch(
+        &self,
+        pipeline: &wgpu::ComputePipeline,
+        bind_groups: &[&wgpu::BindGroup],
+        workgroups: (u32, u32, u32),
+    ) {
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Compute Encoder"),
+            });
+
+        Self::dispatch_with_encoder(&mut encoder, pipeline, bind_groups, workgroups);
+
+        self.queue.submit(std::iter::once(encoder.finish()));
+    }
+
+    /// Record a compute pass into an existing encoder, e.g. the frame encoder passed to
+    /// `VestaApp::render_offscreen`, so the results can be used by the same frame
+    pub fn dispatch_with_encoder(
+        encoder: &mut wgpu::CommandEncoder,
+        pipeline: &wgpu::ComputePipeline,
+        bind_groups: &[&wgpu::BindGroup],
+        workgroups: (u32, u32, u32),
+    ) {
+        let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("Compute Pass"),
+        });
+
+        compute_pass.set_pipeline(pipeline);
+        for (index, bind_group) in bind_groups.iter().enumerate() {
+            compute_pass.set_bind_group(index as u32, bind_group, &[]);
+        }
+        compute_pass.dispatch(workgroups.0, workgroups.1, workgroups.2);
+    }
+}
@@ -1,9 +1,23 @@
-use winit::dpi::PhysicalSize;
+use std::path::PathBuf;
+
+use winit::{dpi::PhysicalSize, event::VirtualKeyCode};
+
+use crate::Tonemapper;
 
 /// Configuration for the vesta engine
 pub struct Config {
     pub window_title: String,
     pub window_size: PhysicalSize<u32>,
+    /// Samples per pixel for multisample anti-aliasing (MSAA) of the main render pass, 1 turns
+    /// it off. 4 is supported by every device
+    pub sample_count: u32,
+    pub hdr: HdrConfig,
+    /// Pass the HDR scene through a `PostProcessStack` (with the default effects) before it
+    /// reaches the screen, see `Engine::post_processing`. This enables HDR rendering, and the
+    /// stack tonemaps with `hdr.tonemapper`
+    pub post_processing: bool,
+    pub screenshot: ScreenshotConfig,
+    pub device: DeviceConfig,
 }
 
 impl Default for Config {
@@ -11,6 +25,93 @@ impl Default for Config {
         Config {
             window_title: "Vesta Engine".to_string(),
             window_size: PhysicalSize::new(800, 600),
+            sample_count: 1,
+            hdr: HdrConfig::default(),
+            post_processing: false,
+            screenshot: ScreenshotConfig::default(),
+            device: DeviceConfig::default(),
+        }
+    }
+}
+
+/// Configuration for choosing the GPU and the features and limits it is created with. The
+/// features that were enabled are available from `Renderer::features`
+#[derive(Clone, Debug)]
+pub struct DeviceConfig {
+    /// The backends to search for an adapter, `None` uses the primary backends (Vulkan, Metal,
+    /// DX12 and WebGPU) natively and all backends on wasm
+    pub backends: Option<wgpu::Backends>,
+    pub power_preference: wgpu::PowerPreference,
+    /// Use the first adapter whose name contains this (ignoring case), falling back to the
+    /// power preference if there is no match. This is ignored on wasm
+    pub adapter_name: Option<String>,
+    /// Features the engine can not start without
+    pub required_features: wgpu::Features,
+    /// Features that are enabled when the adapter supports them. By default this includes
+    /// `TEXTURE_FORMAT_16BIT_NORM`, so 16-bit grayscale images keep their full precision
+    pub optional_features: wgpu::Features,
+    /// The limits to request, `None` uses the defaults (WebGL2 limits on wasm). The push
+    /// constant size is raised to the adapter's maximum if push constants are enabled and no
+    /// size was requested
+    pub limits: Option<wgpu::Limits>,
+}
+
+impl Default for DeviceConfig {
+    fn default() -> Self {
+        Self {
+            backends: None,
+            power_preference: wgpu::PowerPreference::HighPerformance,
+            adapter_name: None,
+            required_features: wgpu::Features::empty(),
+            optional_features: wgpu::Features::POLYGON_MODE_LINE
+                | wgpu::Features::POLYGON_MODE_POINT
+                | wgpu::Features::PUSH_CONSTANTS
+                | wgpu::Features::TEXTURE_FORMAT_16BIT_NORM,
+            limits: None,
+        }
+    }
+}
+
+/// Configuration for rendering the scene in HDR, see `Engine::hdr`
+#[derive(Clone, Debug)]
+pub struct HdrConfig {
+    /// Draw the main render pass into an HDR target (see `Renderer::hdr_format`), which is
+    /// tonemapped onto the surface. Off by default, and disabled automatically if the device
+    /// can't render to it
+    pub enabled: bool,
+    /// The brightness is scaled by this before tonemapping
+    pub exposure: f32,
+    /// Defaults to `Tonemapper::None`, so colors look the same as without HDR
+    pub tonemapper: Tonemapper,
+}
+
+impl Default for HdrConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            exposure: 1.0,
+            tonemapper: Tonemapper::None,
+        }
+    }
+}
+
+/// Configuration for capturing screenshots, see `Engine::capture_screenshot`
+#[derive(Clone, Debug)]
+pub struct ScreenshotConfig {
+    /// Pressing this key saves a timestamped screenshot into `directory`
+    pub hotkey: Option<VirtualKeyCode>,
+    /// Include the egui layer in screenshots
+    pub include_ui: bool,
+    /// Where screenshots taken with the hotkey are saved
+    pub directory: PathBuf,
+}
+
+impl Default for ScreenshotConfig {
+    fn default() -> Self {
+        Self {
+            hotkey: Some(VirtualKeyCode::F12),
+            include_ui: true,
+            directory: PathBuf::from("screenshots"),
         }
     }
 }
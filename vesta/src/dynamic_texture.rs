@@ -0,0 +1,498 @@
+use std::num::NonZeroU32;
+
+use anyhow::*;
+use wgpu::util::DeviceExt;
+
+use crate::{Renderer, Texture, TextureConfig};
+
+/// Past this many separate dirty rectangles, they are merged into one that covers them all
+const MAX_DIRTY_RECTS: usize = 16;
+
+/// A region of the texture, in pixels
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Rect {
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+}
+
+impl Rect {
+    fn right(&self) -> u32 {
+        self.x + self.width
+    }
+
+    fn bottom(&self) -> u32 {
+        self.y + self.height
+    }
+
+    /// If the rectangles overlap or share an edge
+    fn touches(&self, other: &Rect) -> bool {
+        self.x <= other.right()
+            && other.x <= self.right()
+            && self.y <= other.bottom()
+            && other.y <= self.bottom()
+    }
+
+    fn union(&self, other: &Rect) -> Rect {
+        let x = self.x.min(other.x);
+        let y = self.y.min(other.y);
+
+        Rect {
+            x,
+            y,
+            width: self.right().max(other.right()) - x,
+            height: self.bottom().max(other.bottom()) - y,
+        }
+    }
+}
+
+/// A texture that is written to from the CPU every frame (e.g. a simulation or a software
+/// renderer). Pixels are changed in the CPU side buffer, and only the changed regions are
+/// uploaded when the texture is flushed
+pub struct DynamicTexture {
+    /// The GPU texture, bind this to shaders with `texture.bind_group`
+    pub texture: Texture,
+    data: Vec<u8>,
+    bytes_per_pixel: u32,
+    dirty_rects: Vec<Rect>,
+}
+
+impl Renderer {
+    /// Create a dynamic texture, all pixels start as zero
+    pub fn create_dynamic_texture(
+        &self,
+        width: u32,
+        height: u32,
+        format: wgpu::TextureFormat,
+        label: Option<&str>,
+        config: TextureConfig,
+    ) -> Result<DynamicTexture> {
+        DynamicTexture::new(self, width, height, format, label, config)
+    }
+}
+
+impl DynamicTexture {
+    /// Create a dynamic texture, all pixels start as zero. Only the sampler settings of the
+    /// config are used, dynamic textures have no mipmaps. The format must be sampled as floats,
+    /// formats that can't be filtered (e.g. `R32Float`) get a non-filtering sampler, so their
+    /// bind group layout differs from `Texture::create_bind_group_layout`
+    pub fn new(
+        renderer: &Renderer,
+        width: u32,
+        height: u32,
+        format: wgpu::TextureFormat,
+        label: Option<&str>,
+        config: TextureConfig,
+    ) -> Result<Self> {
+        if width == 0 || height == 0 {
+            bail!("Dynamic textures must be at least 1x1");
+        }
+
+        // Shaders sample these as floats, unfilterable formats (e.g. `R32Float`) are sampled
+        // without filtering
+        let format_info = format.describe();
+        let filterable = match format_info.sample_type {
+            wgpu::TextureSampleType::Float { filterable }
+                if format_info.block_dimensions == (1, 1) =>
+            {
+                filterable && format_info.guaranteed_format_features.filterable
+            }
+            _ => bail!("{:?} can not be used for a dynamic texture", format),
+        };
+        let filter = |mode| {
+            if filterable {
+                mode
+            } else {
+                wgpu::FilterMode::Nearest
+            }
+        };
+
+        let device = &renderer.device;
+        let size = wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        };
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label,
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING
+                | wgpu::TextureUsages::COPY_DST
+                | wgpu::TextureUsages::COPY_SRC,
+        });
+
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: config.sampler_address_mode_u,
+            address_mode_v: config.sampler_address_mode_v,
+            address_mode_w: config.sampler_address_mode_w,
+            mag_filter: filter(config.sampler_mag_filter),
+            min_filter: filter(config.sampler_min_filter),
+            mipmap_filter: filter(config.sampler_mipmap_filter),
+            ..Default::default()
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &renderer.get_bind_group_layout(
+                "texture_bind_group_layout",
+                &Texture::layout_entries(wgpu::TextureViewDimension::D2, filterable),
+            ),
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&sampler),
+                },
+            ],
+            label: Some("dynamic_texture_bind_group"),
+        });
+
+        let bytes_per_pixel = format_info.block_size as u32;
+
+        Ok(Self {
+            texture: Texture {
+                texture,
+                view,
+                sampler,
+                bind_group: Some(bind_group),
+                size,
+                format,
+            },
+            data: vec![0; (width * height * bytes_per_pixel) as usize],
+            bytes_per_pixel,
+            // The texture starts uninitialized on the GPU
+            dirty_rects: vec![Rect {
+                x: 0,
+                y: 0,
+                width,
+                height,
+            }],
+        })
+    }
+
+    pub fn width(&self) -> u32 {
+        self.texture.size.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.texture.size.height
+    }
+
+    /// The size of a single pixel in bytes
+    pub fn bytes_per_pixel(&self) -> u32 {
+        self.bytes_per_pixel
+    }
+
+    /// The CPU side pixels, stored row by row starting at the top left
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Mutable access to all of the pixels. The whole texture is uploaded on the next flush,
+    /// use `set_pixel` or `write_rect` to only upload what has changed
+    pub fn data_mut(&mut self) -> &mut [u8] {
+        self.mark_all_dirty();
+        &mut self.data
+    }
+
+    /// The bytes of a single pixel
+    pub fn get_pixel(&self, x: u32, y: u32) -> Option<&[u8]> {
+        if x >= self.width() || y >= self.height() {
+            return None;
+        }
+
+        let index = self.pixel_offset(x, y);
+        Some(&self.data[index..index + self.bytes_per_pixel as usize])
+    }
+
+    /// Set the bytes of a single pixel, pixels outside of the texture are ignored
+    pub fn set_pixel(&mut self, x: u32, y: u32, pixel: &[u8]) {
+        debug_assert_eq!(pixel.len(), self.bytes_per_pixel as usize);
+
+        if x >= self.width() || y >= self.height() {
+            return;
+        }
+
+        let index = self.pixel_offset(x, y);
+        let current = &mut self.data[index..index + self.bytes_per_pixel as usize];
+
+        // Skip marking the pixel dirty if nothing changed
+        if current != pixel {
+            current.copy_from_slice(pixel);
+            self.mark_dirty(x, y, 1, 1);
+        }
+    }
+
+    /// Copy a block of pixels (stored row by row) into the texture
+    pub fn write_rect(
+        &mut self,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+        data: &[u8],
+    ) -> Result<()> {
+        if x + width > self.width() || y + height > self.height() {
+            bail!(
+                "Rectangle {}x{} at ({}, {}) does not fit within the {}x{} texture",
+                width,
+                height,
+                x,
+                y,
+                self.width(),
+                self.height()
+            );
+        }
+
+        let row_size = (width * self.bytes_per_pixel) as usize;
+        if data.len() != row_size * height as usize {
+            bail!(
+                "Expected {} bytes for a {}x{} rectangle, got {}",
+                row_size * height as usize,
+                width,
+                height,
+                data.len()
+            );
+        }
+
+        for row in 0..height {
+            let index = self.pixel_offset(x, y + row);
+            let source = row as usize * row_size;
+            self.data[index..index + row_size].copy_from_slice(&data[source..source + row_size]);
+        }
+
+        self.mark_dirty(x, y, width, height);
+
+        Ok(())
+    }
+
+    /// Mark a region as changed, so it is uploaded on the next flush
+    pub fn mark_dirty(&mut self, x: u32, y: u32, width: u32, height: u32) {
+        let x = x.min(self.width());
+        let y = y.min(self.height());
+        let width = width.min(self.width() - x);
+        let height = height.min(self.height() - y);
+
+        if width == 0 || height == 0 {
+            return;
+        }
+
+        add_dirty_rect(
+            &mut self.dirty_rects,
+            Rect {
+                x,
+                y,
+                width,
+                height,
+            },
+        );
+    }
+
+    /// Upload the whole texture on the next flush
+    pub fn mark_all_dirty(&mut self) {
+        self.dirty_rects = vec![Rect {
+            x: 0,
+            y: 0,
+            width: self.width(),
+            height: self.height(),
+        }];
+    }
+
+    pub fn is_dirty(&self) -> bool {
+        !self.dirty_rects.is_empty()
+    }
+
+    /// Upload the changed regions to the GPU. The uploads happen before the next submitted
+    /// command buffer runs
+    pub fn flush(&mut self, queue: &wgpu::Queue) {
+        for rect in self.dirty_rects.drain(..) {
+            let offset = (rect.y * self.texture.size.width + rect.x) * self.bytes_per_pixel;
+
+            queue.write_texture(
+                wgpu::ImageCopyTexture {
+                    texture: &self.texture.texture,
+                    mip_level: 0,
+                    origin: wgpu::Origin3d {
+                        x: rect.x,
+                        y: rect.y,
+                        z: 0,
+                    },
+                    aspect: wgpu::TextureAspect::All,
+                },
+                &self.data,
+                wgpu::ImageDataLayout {
+                    offset: offset as wgpu::BufferAddress,
+                    bytes_per_row: NonZeroU32::new(self.texture.size.width * self.bytes_per_pixel),
+                    rows_per_image: NonZeroU32::new(rect.height),
+                },
+                wgpu::Extent3d {
+                    width: rect.width,
+                    height: rect.height,
+                    depth_or_array_layers: 1,
+                },
+            );
+        }
+    }
+
+    /// Upload the changed regions through a staging buffer, recording the copies into the
+    /// encoder. Unlike `flush`, this keeps the upload in order with the encoder's other
+    /// commands, so the texture can be updated between passes of the same frame
+    pub fn flush_with_encoder(
+        &mut self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+    ) {
+        if self.dirty_rects.is_empty() {
+            return;
+        }
+
+        // Pack every rectangle into one buffer, with rows padded to the copy alignment
+        let alignment = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+        let mut staging = Vec::new();
+        let mut copies = Vec::with_capacity(self.dirty_rects.len());
+
+        for rect in self.dirty_rects.drain(..) {
+            let row_size = rect.width * self.bytes_per_pixel;
+            let padded_row_size = (row_size + alignment - 1) & !(alignment - 1);
+            let offset = staging.len();
+
+            for row in 0..rect.height {
+                let index = (((rect.y + row) * self.texture.size.width + rect.x)
+                    * self.bytes_per_pixel) as usize;
+                staging.extend_from_slice(&self.data[index..index + row_size as usize]);
+                staging.resize(staging.len() + (padded_row_size - row_size) as usize, 0);
+            }
+
+            copies.push((rect, offset, padded_row_size));
+        }
+
+        let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Dynamic Texture Staging Buffer"),
+            contents: &staging,
+            usage: wgpu::BufferUsages::COPY_SRC,
+        });
+
+        for (rect, offset, padded_row_size) in copies {
+            encoder.copy_buffer_to_texture(
+                wgpu::ImageCopyBuffer {
+                    buffer: &buffer,
+                    layout: wgpu::ImageDataLayout {
+                        offset: offset as wgpu::BufferAddress,
+                        bytes_per_row: NonZeroU32::new(padded_row_size),
+                        rows_per_image: NonZeroU32::new(rect.height),
+                    },
+                },
+                wgpu::ImageCopyTexture {
+                    texture: &self.texture.texture,
+                    mip_level: 0,
+                    origin: wgpu::Origin3d {
+                        x: rect.x,
+                        y: rect.y,
+                        z: 0,
+                    },
+                    aspect: wgpu::TextureAspect::All,
+                },
+                wgpu::Extent3d {
+                    width: rect.width,
+                    height: rect.height,
+                    depth_or_array_layers: 1,
+                },
+            );
+        }
+    }
+
+    fn pixel_offset(&self, x: u32, y: u32) -> usize {
+        ((y * self.texture.size.width + x) * self.bytes_per_pixel) as usize
+    }
+}
+
+/// Add a rectangle to the dirty list, growing any rectangle it touches (and merging again in
+/// case the result now touches others)
+fn add_dirty_rect(dirty_rects: &mut Vec<Rect>, mut rect: Rect) {
+    while let Some(index) = dirty_rects.iter().position(|r| r.touches(&rect)) {
+        rect = rect.union(&dirty_rects.swap_remove(index));
+    }
+    dirty_rects.push(rect);
+
+    if dirty_rects.len() > MAX_DIRTY_RECTS {
+        let bounds = dirty_rects
+            .iter()
+            .skip(1)
+            .fold(dirty_rects[0], |bounds, r| bounds.union(r));
+        *dirty_rects = vec![bounds];
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{add_dirty_rect, Rect, MAX_DIRTY_RECTS};
+
+    fn rect(x: u32, y: u32, width: u32, height: u32) -> Rect {
+        Rect {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    #[test]
+    fn rects_touch_when_overlapping_or_adjacent() {
+        let a = rect(0, 0, 4, 4);
+
+        assert!(a.touches(&rect(2, 2, 4, 4)));
+        assert!(a.touches(&rect(4, 0, 2, 2)));
+        assert!(a.touches(&rect(0, 4, 2, 2)));
+        assert!(!a.touches(&rect(5, 0, 2, 2)));
+        assert!(!a.touches(&rect(0, 5, 2, 2)));
+    }
+
+    #[test]
+    fn union_covers_both_rects() {
+        assert_eq!(rect(0, 0, 2, 2).union(&rect(4, 1, 2, 4)), rect(0, 0, 6, 5));
+        assert_eq!(rect(1, 1, 8, 8).union(&rect(2, 2, 2, 2)), rect(1, 1, 8, 8));
+    }
+
+    #[test]
+    fn keeps_separate_rects_apart() {
+        let mut dirty_rects = Vec::new();
+        add_dirty_rect(&mut dirty_rects, rect(0, 0, 2, 2));
+        add_dirty_rect(&mut dirty_rects, rect(10, 10, 2, 2));
+
+        assert_eq!(dirty_rects, vec![rect(0, 0, 2, 2), rect(10, 10, 2, 2)]);
+    }
+
+    #[test]
+    fn merges_touching_rects_transitively() {
+        let mut dirty_rects = Vec::new();
+        add_dirty_rect(&mut dirty_rects, rect(0, 0, 2, 2));
+        add_dirty_rect(&mut dirty_rects, rect(6, 0, 2, 2));
+
+        // Touches both, so all three become one
+        add_dirty_rect(&mut dirty_rects, rect(2, 0, 4, 1));
+
+        assert_eq!(dirty_rects, vec![rect(0, 0, 8, 2)]);
+    }
+
+    #[test]
+    fn collapses_too_many_rects_into_their_bounds() {
+        let mut dirty_rects = Vec::new();
+        for i in 0..MAX_DIRTY_RECTS as u32 {
+            add_dirty_rect(&mut dirty_rects, rect(i * 4, i * 4, 1, 1));
+        }
+        assert_eq!(dirty_rects.len(), MAX_DIRTY_RECTS);
+
+        let last = MAX_DIRTY_RECTS as u32 * 4;
+        add_dirty_rect(&mut dirty_rects, rect(last, last, 1, 1));
+
+        assert_eq!(dirty_rects, vec![rect(0, 0, last + 1, last + 1)]);
+    }
+}
@@ -1,8 +1,12 @@
+use std::path::PathBuf;
+
 use crate::{
-    config::Config,
+    blit::Blitter,
+    config::{Config, DeviceConfig, ScreenshotConfig},
     io::{Keyboard, Mouse, IO},
+    recording::{Recorder, RecordingConfig},
     renderer::Renderer,
-    texture, VestaApp,
+    texture, HdrPass, PostProcessStack, RenderTarget, RenderTargetConfig, VestaApp,
 };
 use winit::{
     event::{Event, WindowEvent},
@@ -21,12 +25,29 @@ pub struct Time {
     current_time: instant::Instant,
     start_time: instant::Instant,
     accumulator: f32,
+    fixed_frame_time: Option<f32>,
 }
 
 impl Time {
     pub fn get_delta_time(&self) -> f32 {
         self.frame_delta_time
     }
+
+    /// Advance time by exactly this many seconds each frame instead of the real frame time,
+    /// so the simulation runs the same no matter how fast frames are rendered
+    pub fn set_fixed_frame_time(&mut self, fixed_frame_time: Option<f32>) {
+        self.fixed_frame_time = fixed_frame_time;
+    }
+
+    pub fn get_fixed_frame_time(&self) -> Option<f32> {
+        self.fixed_frame_time
+    }
+}
+
+/// A screenshot that will be captured when the next frame is rendered
+struct PendingScreenshot {
+    path: PathBuf,
+    include_ui: bool,
 }
 
 pub struct Engine {
@@ -37,6 +58,19 @@ pub struct Engine {
     cursor_captured: bool,
     // Timing
     pub time: Time,
+    // Screenshots
+    pub screenshot_config: ScreenshotConfig,
+    pending_screenshot: Option<PendingScreenshot>,
+    // Recording
+    recorder: Option<Recorder>,
+    fixed_frame_time_before_recording: Option<f32>,
+    // Frames that are captured are rendered here, and then blitted onto the surface
+    capture_target: Option<RenderTarget>,
+    blitter: Option<Blitter>,
+    /// The HDR scene target and its tonemapping, when enabled with `Config::hdr`
+    pub hdr: Option<HdrPass>,
+    /// The effects applied to the scene, when enabled with `Config::post_processing`
+    pub post_processing: Option<PostProcessStack>,
 }
 
 impl Engine {
@@ -104,23 +138,25 @@ impl Engine {
         let window_size = window.inner_size();
 
         // New WGPU instance and surface to render on
-        let instance = wgpu::Instance::new(Self::determine_backends());
+        let backends = config
+            .device
+            .backends
+            .unwrap_or_else(Self::determine_backends);
+        let instance = wgpu::Instance::new(backends);
         let surface = unsafe { instance.create_surface(&window) };
 
-        // Request a high performance adapter
-        let adapter = instance
-            .request_adapter(&wgpu::RequestAdapterOptions {
-                power_preference: wgpu::PowerPreference::HighPerformance,
-                compatible_surface: Some(&surface),
-                force_fallback_adapter: false,
-            })
-            .await
-            .unwrap();
+        let adapter = Self::select_adapter(&instance, &surface, backends, &config.device).await;
+        let adapter_info = adapter.get_info();
+        log::info!(
+            "Using adapter '{}' ({:?})",
+            adapter_info.name,
+            adapter_info.backend
+        );
 
         // Request a device and queue
         let (device, queue) = adapter
             .request_device(
-                &Self::determine_device_descriptor(),
+                &Self::determine_device_descriptor(&adapter, &config.device),
                 None, // Trace path
             )
             .await
@@ -137,10 +173,49 @@ impl Engine {
         };
         surface.configure(&device, &surface_config);
 
-        // Create a depth texture
-        let depth_texture =
-            texture::Texture::create_depth(&device, &surface_config, Some("Depth Texture"))
-                .unwrap();
+        // Multisampling needs a power of two sample count
+        let sample_count = if config.sample_count.is_power_of_two() {
+            config.sample_count
+        } else {
+            log::warn!(
+                "Sample count {} is not a power of two, multisampling is disabled",
+                config.sample_count
+            );
+            1
+        };
+
+        // With HDR the scene is drawn into a float target instead of the surface, post
+        // processing works on HDR colors so it needs this too
+        let wants_hdr = config.hdr.enabled || config.post_processing;
+        let hdr = wants_hdr && HdrPass::is_supported(&adapter);
+        if wants_hdr && !hdr {
+            log::warn!(
+                "The adapter can't render to {:?} targets, HDR rendering and post processing \
+                are disabled",
+                Renderer::HDR_FORMAT
+            );
+        }
+        let scene_format = if hdr {
+            Renderer::HDR_FORMAT
+        } else {
+            surface_format
+        };
+
+        // Create a depth texture, and a color texture to resolve from when multisampling
+        let depth_texture = texture::Texture::create_depth_with_size(
+            &device,
+            surface_config.width,
+            surface_config.height,
+            sample_count,
+            Some("Depth Texture"),
+        )
+        .unwrap();
+        let multisampled_framebuffer = Renderer::create_multisampled_framebuffer(
+            &device,
+            &surface_config,
+            scene_format,
+            sample_count,
+        );
 
         // -------------- GUI ------------------ //
 
@@ -154,7 +229,8 @@ impl Engine {
                 style: Default::default(),
             });
 
-        // Create the renderer (wgpu)
+        // Create the renderer (wgpu). The UI is drawn after the main pass has been resolved, so
+        // it is never multisampled
         let gui_renderer = egui_wgpu_backend::RenderPass::new(&device, surface_format, 1);
 
         // Renderer information, this will be sent to the app implementation so it can access resources
@@ -164,8 +240,32 @@ impl Engine {
             queue,
             surface_config,
             depth_texture,
+            sample_count,
+            hdr,
+            multisampled_framebuffer,
+            bind_group_layouts: Default::default(),
+            adapter_info,
         };
 
+        // Post processing tonemaps the scene itself, so the pass only needs the scene target
+        let hdr = hdr.then(|| {
+            if config.post_processing {
+                HdrPass::without_tonemap(&renderer, &config.hdr).unwrap()
+            } else {
+                HdrPass::new(&renderer, &config.hdr).unwrap()
+            }
+        });
+
+        // The stack tonemaps the scene itself, starting with the configured exposure
+        let post_processing = (config.post_processing && hdr.is_some()).then(|| {
+            let mut stack = PostProcessStack::with_default_effects(&renderer).unwrap();
+            if let Some(tonemap) = stack.effect_mut("Tonemap") {
+                tonemap.set_param("Exposure", config.hdr.exposure);
+                tonemap.set_tonemapper(config.hdr.tonemapper);
+            }
+            stack
+        });
+
         let mut gui = Gui {
             platform: gui_platform,
             renderer: gui_renderer,
@@ -186,7 +286,16 @@ impl Engine {
                 current_time: instant::Instant::now(),
                 start_time: instant::Instant::now(),
                 accumulator: 0.0,
+                fixed_frame_time: None,
             },
+            screenshot_config: config.screenshot,
+            pending_screenshot: None,
+            recorder: None,
+            fixed_frame_time_before_recording: None,
+            capture_target: None,
+            blitter: None,
+            hdr,
+            post_processing,
         };
 
         // First initialize all the apps resources (shaders, pipelines etc.)
@@ -220,19 +329,76 @@ impl Engine {
         }
     }
 
-    fn determine_device_descriptor() -> wgpu::DeviceDescriptor<'static> {
-        // WASM
+    /// Find the adapter named in the config, or request one with the configured power
+    /// preference
+    async fn select_adapter(
+        instance: &wgpu::Instance,
+        surface: &wgpu::Surface,
+        backends: wgpu::Backends,
+        config: &DeviceConfig,
+    ) -> wgpu::Adapter {
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(name) = &config.adapter_name {
+            let name = name.to_lowercase();
+            let adapter = instance.enumerate_adapters(backends).find(|adapter| {
+                adapter.is_surface_supported(surface)
+                    && adapter.get_info().name.to_lowercase().contains(&name)
+            });
+
+            match adapter {
+                Some(adapter) => return adapter,
+                None => log::warn!(
+                    "No adapter matching '{}' was found, using the default adapter",
+                    name
+                ),
+            }
+        }
+
         #[cfg(target_arch = "wasm32")]
-        {
-            return wgpu::DeviceDescriptor {
-                limits: wgpu::Limits::downlevel_webgl2_defaults(),
-                ..Default::default()
-            };
+        let _ = backends;
+
+        instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: config.power_preference,
+                compatible_surface: Some(surface),
+                force_fallback_adapter: false,
+            })
+            .await
+            .expect("No compatible graphics adapter was found")
+    }
+
+    fn determine_device_descriptor(
+        adapter: &wgpu::Adapter,
+        config: &DeviceConfig,
+    ) -> wgpu::DeviceDescriptor<'static> {
+        let missing_features = config.required_features - adapter.features();
+        if !missing_features.is_empty() {
+            panic!(
+                "The adapter '{}' does not support the required features {:?}",
+                adapter.get_info().name,
+                missing_features
+            );
         }
 
-        #[cfg(not(target_arch = "wasm32"))]
-        {
-            return wgpu::DeviceDescriptor::default();
+        // Optional features are only enabled when the adapter supports them
+        let features = config.required_features | (config.optional_features & adapter.features());
+
+        let mut limits = config.limits.clone().unwrap_or_else(|| {
+            if cfg!(target_arch = "wasm32") {
+                wgpu::Limits::downlevel_webgl2_defaults()
+            } else {
+                wgpu::Limits::default()
+            }
+        });
+
+        if features.contains(wgpu::Features::PUSH_CONSTANTS) && limits.max_push_constant_size == 0 {
+            limits.max_push_constant_size = adapter.limits().max_push_constant_size;
+        }
+
+        wgpu::DeviceDescriptor {
+            label: None,
+            features,
+            limits,
         }
     }
 
@@ -253,12 +419,15 @@ impl Engine {
 
                 // Timing logic
                 let new_time = instant::Instant::now();
-                let frame_time = new_time - self.time.current_time;
+                let frame_time = self
+                    .time
+                    .fixed_frame_time
+                    .unwrap_or_else(|| (new_time - self.time.current_time).as_secs_f32());
 
-                self.time.frame_delta_time = frame_time.as_secs_f32();
+                self.time.frame_delta_time = frame_time;
 
                 self.time.current_time = new_time;
-                self.time.accumulator += frame_time.as_secs_f32();
+                self.time.accumulator += frame_time;
 
                 while self.time.accumulator >= self.time.delta_time {
                     app.physics_update(self.time.delta_time, self);
@@ -268,6 +437,12 @@ impl Engine {
                 // Run the frame update
                 app.update(self);
 
+                if let Some(hotkey) = self.screenshot_config.hotkey {
+                    if self.io.keyboard.get_key_down(hotkey) {
+                        self.capture_screenshot_timestamped();
+                    }
+                }
+
                 self.io.mouse.clear_events();
                 self.io.keyboard.clear_events();
 
@@ -299,7 +474,11 @@ impl Engine {
                 }
 
                 match event {
-                    WindowEvent::CloseRequested => *control_flow = ControlFlow::Exit,
+                    WindowEvent::CloseRequested => {
+                        // Make sure recordings are fully written before exiting
+                        self.end_recording();
+                        *control_flow = ControlFlow::Exit
+                    }
                     WindowEvent::Resized(physical_size) => self.resize(app, *physical_size),
                     WindowEvent::ScaleFactorChanged { new_inner_size, .. } => {
                         self.resize(app, **new_inner_size)
@@ -327,6 +506,18 @@ impl Engine {
             .renderer
             .create_depth_texture(Some("Depth Texture"))
             .unwrap();
+        self.renderer.multisampled_framebuffer = Renderer::create_multisampled_framebuffer(
+            &self.renderer.device,
+            &self.renderer.surface_config,
+            self.renderer.scene_format(),
+            self.renderer.sample_count,
+        );
+        if let Some(hdr) = &mut self.hdr {
+            hdr.resize(&self.renderer).unwrap();
+        }
+        if let Some(post_processing) = &mut self.post_processing {
+            post_processing.resize(&self.renderer).unwrap();
+        }
 
         // Run any app specific events
         app.resize(new_size, self);
@@ -343,18 +534,55 @@ impl Engine {
             .texture
             .create_view(&wgpu::TextureViewDescriptor::default());
 
+        // Work out which images of this frame are needed for screenshots and recordings
+        let screenshot = self.pending_screenshot.take();
+        let (record_without_ui, record_with_ui) = match &self.recorder {
+            Some(recorder) => (!recorder.config.include_ui, recorder.config.include_ui),
+            None => (false, false),
+        };
+        let capture_without_ui =
+            record_without_ui || matches!(&screenshot, Some(screenshot) if !screenshot.include_ui);
+        let capture_with_ui =
+            record_with_ui || matches!(&screenshot, Some(screenshot) if screenshot.include_ui);
+
+        // Captured frames are rendered into a texture that can be copied, instead of the surface
+        let capture_target = if capture_without_ui || capture_with_ui {
+            self.take_capture_target()
+        } else {
+            None
+        };
+        let frame_view = match &capture_target {
+            Some(target) => &target.color.view,
+            None => &out_view,
+        };
+
         let mut encoder = self
             .renderer
             .device
             .create_command_encoder(&Default::default());
 
+        // ---- OFFSCREEN ---- //
+        app.render_offscreen(&mut encoder, self);
+
         // ---- MAIN ---- //
         {
+            // The scene is drawn into the HDR target if there is one
+            let scene_view = match &self.hdr {
+                Some(hdr) => &hdr.scene_target().color.view,
+                None => frame_view,
+            };
+
+            // When multisampling, draw into the multisampled texture and resolve it to the scene
+            let (view, resolve_target) = match &self.renderer.multisampled_framebuffer {
+                Some(multisampled_framebuffer) => (multisampled_framebuffer, Some(scene_view)),
+                None => (scene_view, None),
+            };
+
             let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                 label: Some("Render Pass"),
                 color_attachments: &[wgpu::RenderPassColorAttachment {
-                    view: &out_view,
-                    resolve_target: None,
+                    view,
+                    resolve_target,
                     ops: wgpu::Operations {
                         load: wgpu::LoadOp::Clear(wgpu::Color {
                             r: 0.0,
@@ -378,6 +606,32 @@ impl Engine {
             app.render(&mut render_pass, self)
         }
 
+        // ---- HDR RESOLVE ---- //
+        if let Some(hdr) = &mut self.hdr {
+            match &mut self.post_processing {
+                Some(post_processing) => post_processing.apply(
+                    &mut encoder,
+                    &self.renderer,
+                    hdr.scene_target(),
+                    frame_view,
+                ),
+                None => hdr.resolve(&mut encoder, &self.renderer, frame_view),
+            }
+        }
+
+        // Capture the frame before the UI is drawn over it
+        let mut image_without_ui = None;
+        if let (true, Some(target)) = (capture_without_ui, &capture_target) {
+            self.renderer
+                .queue
+                .submit(std::iter::once(encoder.finish()));
+            encoder = self
+                .renderer
+                .device
+                .create_command_encoder(&Default::default());
+            image_without_ui = Some(Self::read_frame(target, &self.renderer));
+        }
+
         // ---- UI ---- //
         {
             // Start rendering frame
@@ -386,6 +640,14 @@ impl Engine {
             // Render app UI
             app.render_ui(&gui.platform.context(), self);
 
+            if let Some(post_processing) = &mut self.post_processing {
+                let mut open = post_processing.show_ui;
+                egui::Window::new("Post Processing")
+                    .open(&mut open)
+                    .show(&gui.platform.context(), |ui| post_processing.ui(ui));
+                post_processing.show_ui = open;
+            }
+
             // End the UI frame. We could now handle the output and draw the UI with the backend.
             let (_output, paint_commands) = gui.platform.end_frame(Some(&self.window));
             let paint_jobs = gui.platform.context().tessellate(paint_commands);
@@ -415,7 +677,7 @@ impl Engine {
             gui.renderer
                 .execute(
                     &mut encoder,
-                    &out_view,
+                    frame_view,
                     &paint_jobs,
                     &screen_descriptor,
                     None,
@@ -423,16 +685,220 @@ impl Engine {
                 .expect("Failed to render UI!");
         }
 
+        // Show the captured frame on screen
+        if let Some(target) = &capture_target {
+            let renderer = &self.renderer;
+            self.blitter
+                .get_or_insert_with(|| {
+                    Blitter::new(renderer.surface_config.format, &renderer.device)
+                })
+                .blit(&mut encoder, &target.color, &out_view);
+        }
+
         // Finished with the frame
         self.renderer
             .queue
             .submit(std::iter::once(encoder.finish()));
 
+        let mut image_with_ui = None;
+        if let (true, Some(target)) = (capture_with_ui, &capture_target) {
+            image_with_ui = Some(Self::read_frame(target, &self.renderer));
+        }
+
         output.present();
 
+        if let Some(screenshot) = screenshot {
+            let image = if screenshot.include_ui {
+                image_with_ui.as_ref()
+            } else {
+                image_without_ui.as_ref()
+            };
+            self.save_screenshot(screenshot, image, app);
+        }
+
+        if let Some(recorder) = &self.recorder {
+            let image = if recorder.config.include_ui {
+                image_with_ui
+            } else {
+                image_without_ui
+            };
+            self.record_frame(image);
+        }
+
+        // Keep the target around for the next recorded frame
+        if self.recorder.is_some() {
+            self.capture_target = capture_target;
+        }
+
+        Ok(())
+    }
+
+    /// Reuse the capture target from the previous frame, or create a new one
+    fn take_capture_target(&mut self) -> Option<RenderTarget> {
+        let target = match self.capture_target.take() {
+            Some(mut target) => target.resize(&self.renderer).map(|_| target),
+            // The capture is drawn after tonemapping, so it uses the surface format
+            None => RenderTarget::new(
+                RenderTargetConfig {
+                    format: Some(self.renderer.surface_config.format),
+                    depth: false,
+                    ..Default::default()
+                },
+                "Frame Capture",
+                &self.renderer,
+            ),
+        };
+
+        target
+            .map_err(|e| log::error!("Failed to create frame capture target: {}", e))
+            .ok()
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn read_frame(target: &RenderTarget, renderer: &Renderer) -> anyhow::Result<image::RgbaImage> {
+        target.read_to_image(renderer)
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn read_frame(
+        _target: &RenderTarget,
+        _renderer: &Renderer,
+    ) -> anyhow::Result<image::RgbaImage> {
+        anyhow::bail!("Capturing frames is not supported on the web")
+    }
+
+    fn save_screenshot<V: VestaApp>(
+        &mut self,
+        screenshot: PendingScreenshot,
+        image: Option<&anyhow::Result<image::RgbaImage>>,
+        app: &mut V,
+    ) {
+        let image = match image {
+            Some(Ok(image)) => image,
+            Some(Err(e)) => {
+                log::error!("Failed to capture screenshot: {}", e);
+                return;
+            }
+            // The capture target could not be created, which has already been logged
+            None => return,
+        };
+
+        if let Some(directory) = screenshot.path.parent() {
+            std::fs::create_dir_all(directory).ok();
+        }
+
+        match image.save(&screenshot.path) {
+            Ok(_) => log::info!("Saved screenshot to {}", screenshot.path.display()),
+            Err(e) => log::error!(
+                "Failed to save screenshot to {}: {}",
+                screenshot.path.display(),
+                e
+            ),
+        }
+
+        app.screenshot_captured(image, self);
+    }
+
+    fn record_frame(&mut self, image: Option<anyhow::Result<image::RgbaImage>>) {
+        let recorder = match &mut self.recorder {
+            Some(recorder) => recorder,
+            None => return,
+        };
+
+        let result = image
+            .unwrap_or_else(|| Err(anyhow::anyhow!("The frame could not be captured")))
+            .and_then(|image| recorder.add_frame(image));
+
+        // A missing frame would ruin the recording, so stop straight away
+        if let Err(e) = result {
+            log::error!("Failed to record frame: {}", e);
+            self.end_recording();
+        } else if recorder.is_finished() {
+            self.end_recording();
+        }
+    }
+
+    /// Start recording every frame to a PNG sequence, GIF or APNG. While recording, time
+    /// advances at exactly `1 / frame_rate` seconds per frame (see `Time::set_fixed_frame_time`),
+    /// so the recording plays back smoothly no matter how slowly frames were rendered
+    pub fn start_recording(&mut self, config: RecordingConfig) -> anyhow::Result<()> {
+        if cfg!(target_arch = "wasm32") {
+            anyhow::bail!("Recording is not supported on the web");
+        }
+
+        if self.recorder.is_some() {
+            anyhow::bail!("A recording is already in progress");
+        }
+
+        let frame_rate = config.frame_rate;
+        self.recorder = Some(Recorder::new(config)?);
+
+        self.fixed_frame_time_before_recording = self.time.fixed_frame_time;
+        self.time.fixed_frame_time = Some(1.0 / frame_rate as f32);
+
         Ok(())
     }
 
+    /// Stop recording and finish writing the output, returning the number of frames recorded
+    pub fn stop_recording(&mut self) -> anyhow::Result<u32> {
+        let recorder = self
+            .recorder
+            .take()
+            .ok_or_else(|| anyhow::anyhow!("There is no recording in progress"))?;
+
+        self.time.fixed_frame_time = self.fixed_frame_time_before_recording.take();
+        self.capture_target = None;
+
+        let path = recorder.config.path.clone();
+        let frame_count = recorder.finish()?;
+        log::info!(
+            "Saved {} recorded frames to {}",
+            frame_count,
+            path.display()
+        );
+
+        Ok(frame_count)
+    }
+
+    /// Stop the recording (if there is one), logging any errors
+    fn end_recording(&mut self) {
+        if self.recorder.is_some() {
+            if let Err(e) = self.stop_recording() {
+                log::error!("Failed to save recording: {}", e);
+            }
+        }
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recorder.is_some()
+    }
+
+    /// Capture the next rendered frame and save it as a PNG (or any format supported by the
+    /// `image` crate, based on the extension). The image is also passed to
+    /// `VestaApp::screenshot_captured`
+    pub fn capture_screenshot<P: Into<PathBuf>>(&mut self, path: P) {
+        self.pending_screenshot = Some(PendingScreenshot {
+            path: path.into(),
+            include_ui: self.screenshot_config.include_ui,
+        });
+    }
+
+    /// Capture the next rendered frame into the screenshot directory, named after the
+    /// current time
+    pub fn capture_screenshot_timestamped(&mut self) {
+        let timestamp = instant::SystemTime::now()
+            .duration_since(instant::SystemTime::UNIX_EPOCH)
+            .map(|duration| duration.as_millis())
+            .unwrap_or_default();
+
+        let path = self
+            .screenshot_config
+            .directory
+            .join(format!("screenshot-{}.png", timestamp));
+
+        self.capture_screenshot(path);
+    }
+
     /// Gets the current window size
     pub fn get_window_size(&self) -> winit::dpi::PhysicalSize<u32> {
         self.window_size
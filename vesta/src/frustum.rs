@@ -1,5 +1,7 @@
 use cgmath::{InnerSpace, Matrix, Matrix3, Matrix4, Vector3, Vector4};
 
+use crate::Aabb;
+
 type Plane = usize;
 
 const PLANE_LEFT: Plane = 0;
@@ -195,4 +197,9 @@ impl Frustum {
 
         true
     }
+
+    /// Check if the bounding box is visible (e.g. a mesh's bounds transformed into world space)
+    pub fn is_aabb_visible(&self, aabb: &Aabb) -> bool {
+        self.is_box_visible(aabb.min, aabb.max)
+    }
 }
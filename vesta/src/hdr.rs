@@ -0,0 +1,86 @@
+use anyhow::*;
+
+use crate::{HdrConfig, PostEffect, RenderTarget, RenderTargetConfig, Renderer, Tonemapper};
+
+/// The HDR target the main render pass draws into, and the pass that tonemaps it onto the
+/// surface. The engine creates this when HDR rendering is enabled, see `Engine::hdr`
+pub struct HdrPass {
+    /// The brightness is scaled by this before tonemapping. Unused when a `PostProcessStack`
+    /// tonemaps the scene instead, see `without_tonemap`
+    pub exposure: f32,
+    pub tonemapper: Tonemapper,
+    scene: RenderTarget,
+    /// The effect and its input sampler, `None` when the scene is tonemapped elsewhere
+    tonemap: Option<(PostEffect, wgpu::Sampler)>,
+}
+
+impl HdrPass {
+    /// Create the scene target and the pass that tonemaps it onto the surface
+    pub fn new(renderer: &Renderer, config: &HdrConfig) -> Result<Self> {
+        let mut pass = Self::without_tonemap(renderer, config)?;
+        pass.tonemap = Some((
+            PostEffect::tonemap_with_format(
+                renderer,
+                renderer.surface_config.format,
+                config.tonemapper,
+                config.exposure,
+            )?,
+            crate::post_process::create_sampler(renderer),
+        ));
+
+        Ok(pass)
+    }
+
+    /// Create only the scene target, for when a `PostProcessStack` tonemaps it. `resolve` does
+    /// nothing for these passes
+    pub fn without_tonemap(renderer: &Renderer, config: &HdrConfig) -> Result<Self> {
+        let scene = renderer.create_render_target(
+            RenderTargetConfig {
+                format: Some(Renderer::HDR_FORMAT),
+                depth: false,
+                ..Default::default()
+            },
+            "HDR Scene Target",
+        )?;
+
+        Ok(Self {
+            exposure: config.exposure,
+            tonemapper: config.tonemapper,
+            scene,
+            tonemap: None,
+        })
+    }
+
+    /// If the device can render to and sample from HDR targets (WebGL2 may not)
+    pub fn is_supported(adapter: &wgpu::Adapter) -> bool {
+        adapter
+            .get_texture_format_features(Renderer::HDR_FORMAT)
+            .allowed_usages
+            .contains(wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING)
+    }
+
+    /// The target the main render pass resolves into
+    pub fn scene_target(&self) -> &RenderTarget {
+        &self.scene
+    }
+
+    /// Recreate the scene target to match the window size
+    pub fn resize(&mut self, renderer: &Renderer) -> Result<()> {
+        self.scene.resize(renderer)?;
+        Ok(())
+    }
+
+    /// Tonemap the scene into the output, which must be the surface format
+    pub fn resolve(
+        &mut self,
+        encoder: &mut wgpu::CommandEncoder,
+        renderer: &Renderer,
+        output: &wgpu::TextureView,
+    ) {
+        if let Some((tonemap, sampler)) = &mut self.tonemap {
+            tonemap.set_param("Exposure", self.exposure);
+            tonemap.set_tonemapper(self.tonemapper);
+            tonemap.apply(encoder, renderer, sampler, &self.scene, output);
+        }
+    }
+}
@@ -0,0 +1,143 @@
+use bytemuck::cast_slice;
+use bytemuck::Pod;
+use bytemuck::Zeroable;
+use cgmath::{Matrix3, Matrix4, Vector3, Vector4};
+use wgpu::util::BufferInitDescriptor;
+use wgpu::util::DeviceExt;
+
+use crate::{components::Transform, VertexLayout};
+
+/// The vertex buffer slot instance data is read from, after the mesh's vertex buffer
+pub const INSTANCE_BUFFER_SLOT: u32 = 1;
+
+/// Per-instance data used when drawing many copies of the same mesh. In the shader,
+/// this is read from `INSTANCE_BUFFER_SLOT` at locations 4 to 11:
+///
+/// ```wgsl
+/// struct InstanceInput {
+///     [[location(4)]] model_0: vec4<f32>;
+///     [[location(5)]] model_1: vec4<f32>;
+///     [[location(6)]] model_2: vec4<f32>;
+///     [[location(7)]] model_3: vec4<f32>;
+///     [[location(8)]] normal_0: vec3<f32>;
+///     [[location(9)]] normal_1: vec3<f32>;
+///     [[location(10)]] normal_2: vec3<f32>;
+///     [[location(11)]] color: vec4<f32>;
+/// };
+/// ```
+///
+/// Combine this with a mesh vertex type using `RenderPipelineBuilder::with_instance_type`
+#[repr(C)]
+#[derive(Copy, Clone, Debug, VertexLayout)]
+#[vertex(instance, start_location = 4)]
+pub struct InstanceData {
+    pub model: Matrix4<f32>,
+    pub normal: Matrix3<f32>,
+    pub color: Vector4<f32>,
+}
+
+unsafe impl Zeroable for InstanceData {}
+unsafe impl Pod for InstanceData {}
+
+impl InstanceData {
+    pub fn new(model: Matrix4<f32>, normal: Matrix3<f32>, color: Vector4<f32>) -> Self {
+        Self {
+            model,
+            normal,
+            color,
+        }
+    }
+
+    /// Create instance data from a transform, with the specified color
+    pub fn from_transform(transform: &Transform<f32>, color: Vector3<f32>) -> Self {
+        Self {
+            model: transform.calculate_model_matrix(),
+            normal: transform.calculate_normal_matrix(),
+            color: color.extend(1.0),
+        }
+    }
+}
+
+// ----------------------------------------- //
+
+/// A holder for a buffer of per-instance data, contains the data and raw buffer. The buffer
+/// grows automatically when more instances are written than it can hold
+pub struct InstanceBuffer<T>
+where
+    T: Pod,
+{
+    pub data: Vec<T>,
+    buffer: wgpu::Buffer,
+    name: String,
+    capacity: usize,
+    count: u32,
+}
+
+impl crate::Renderer {
+    /// Create a new instance buffer containing the specified instances
+    pub fn create_instance_buffer<T: Pod>(&self, name: &str, data: Vec<T>) -> InstanceBuffer<T> {
+        InstanceBuffer::new(name, data, &self.device)
+    }
+
+    /// Write the specified instance buffer to the GPU
+    pub fn write_instance_buffer<T: Pod>(&self, instance_buffer: &mut InstanceBuffer<T>) {
+        instance_buffer.write(&self.device, &self.queue);
+    }
+}
+
+impl<T: Pod> InstanceBuffer<T> {
+    /// Create a new instance buffer to store instances of type
+    pub fn new(name: &str, data: Vec<T>, device: &wgpu::Device) -> Self {
+        // Zero sized buffers cannot be bound, so always have space for at least one instance
+        let buffer = if data.is_empty() {
+            Self::create_buffer(name, 1, device)
+        } else {
+            device.create_buffer_init(&BufferInitDescriptor {
+                label: Some(name),
+                contents: cast_slice(&data),
+                usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            })
+        };
+
+        Self {
+            capacity: data.len().max(1),
+            count: data.len() as u32,
+            data,
+            buffer,
+            name: name.to_string(),
+        }
+    }
+
+    /// Upload the current data to the GPU, growing the buffer if required
+    pub fn write(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
+        if self.data.len() > self.capacity {
+            // Double the capacity to leave room for more instances
+            self.capacity = self.data.len().max(self.capacity * 2);
+            self.buffer = Self::create_buffer(&self.name, self.capacity, device);
+        }
+
+        if !self.data.is_empty() {
+            queue.write_buffer(&self.buffer, 0, cast_slice(&self.data));
+        }
+
+        self.count = self.data.len() as u32;
+    }
+
+    /// The amount of instances that were last written to the GPU
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+
+    pub fn buffer(&self) -> &wgpu::Buffer {
+        &self.buffer
+    }
+
+    fn create_buffer(name: &str, capacity: usize, device: &wgpu::Device) -> wgpu::Buffer {
+        device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some(name),
+            size: (capacity * std::mem::size_of::<T>()) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        })
+    }
+}
@@ -1,31 +1,66 @@
+pub mod bind_group;
+mod blit;
+pub mod bounds;
+pub mod bvh;
 pub mod camera;
 pub mod components;
+pub mod compute;
 pub mod config;
+pub mod dynamic_texture;
 pub mod engine;
 pub mod frustum;
+pub mod hdr;
+pub mod instance_buffer;
 pub mod io;
 pub mod math;
+pub mod post_process;
 pub mod projections;
+pub mod push_constants;
+pub mod recording;
 pub mod render_pipeline;
+pub mod render_target;
 pub mod renderer;
 pub mod scene;
+pub mod shaders;
+pub mod skybox;
+pub mod storage_buffer;
 pub mod texture;
+pub mod texture_atlas;
+pub mod uniform_arena;
 pub mod uniform_buffer;
 pub mod vertex;
 
+pub use bind_group::*;
+pub use bounds::*;
+pub use bvh::*;
 pub use camera::*;
+pub use compute::*;
 pub use config::*;
+pub use dynamic_texture::*;
 pub use engine::*;
 pub use frustum::*;
+pub use hdr::*;
+pub use instance_buffer::*;
 pub use math::*;
+pub use post_process::*;
 pub use projections::*;
+pub use push_constants::*;
+pub use recording::*;
 pub use render_pipeline::*;
+pub use render_target::*;
 pub use renderer::*;
 pub use scene::*;
+pub use skybox::*;
+pub use storage_buffer::*;
 pub use texture::*;
+pub use texture_atlas::*;
+pub use uniform_arena::*;
 pub use uniform_buffer::*;
 pub use vertex::*;
 
+// Allows the derive macros to refer to `::vesta` from within this crate
+extern crate self as vesta;
+
 // These are exposed in the vesta API, so allow dependents to access them
 pub extern crate bytemuck;
 pub extern crate cgmath;
@@ -56,6 +91,10 @@ pub trait VestaApp {
     /// Engine is mutable here on purpose
     fn update(&mut self, engine: &mut Engine) {}
 
+    /// Optional: Render into offscreen render targets, this is called every frame before
+    /// the main render pass (using the same command encoder)
+    fn render_offscreen(&mut self, encoder: &mut wgpu::CommandEncoder, engine: &Engine) {}
+
     /// Optional: Render your UI in this method using egui
     fn render_ui(&mut self, ctx: &egui::CtxRef, engine: &Engine) {}
 
@@ -63,6 +102,12 @@ pub trait VestaApp {
     /// render passes are not yet supported
     fn render<'a>(&'a mut self, render_pass: &mut wgpu::RenderPass<'a>, engine: &Engine);
 
-    /// Optional: Called when the window is resized, update your camera matrixes here
+    /// Optional: Called after a screenshot has been captured and saved, see
+    /// `Engine::capture_screenshot`
+    fn screenshot_captured(&mut self, screenshot: &image::RgbaImage, engine: &Engine) {}
+
+    /// Optional: Called when the window is resized, update your camera matrixes here. Window
+    /// relative render targets only resize themselves when they are next rendered into, call
+    /// `RenderTarget::resize` here for any that are sampled without being rendered into
     fn resize(&mut self, size: winit::dpi::PhysicalSize<u32>, engine: &Engine) {}
 }
@@ -0,0 +1,720 @@
+use std::ops::RangeInclusive;
+use std::sync::Arc;
+
+use anyhow::*;
+use bytemuck::{Pod, Zeroable};
+
+use crate::blit::Blitter;
+use crate::{
+    BindGroupBuilder, BlendMode, RenderPipelineBuilder, RenderTarget, RenderTargetConfig,
+    RenderTargetSize, Renderer, Texture,
+};
+
+/// The most parameters a single effect can have
+const MAX_PARAMS: usize = 8;
+
+/// The parameters of an effect, see `EffectParams` in `shaders::POST_PROCESS`
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+struct EffectUniform {
+    values: [f32; MAX_PARAMS],
+    texel_size: [f32; 4],
+}
+
+unsafe impl Zeroable for EffectUniform {}
+unsafe impl Pod for EffectUniform {}
+
+/// How HDR colors are mapped into the 0 to 1 range of the screen
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Tonemapper {
+    /// Clamp colors, anything brighter than 1.0 is lost
+    None,
+    Reinhard,
+    /// A fit of the ACES filmic curve, with more contrast than Reinhard
+    Aces,
+}
+
+impl Tonemapper {
+    fn from_param(value: f32) -> Self {
+        match value as u32 {
+            1 => Tonemapper::Reinhard,
+            2 => Tonemapper::Aces,
+            _ => Tonemapper::None,
+        }
+    }
+
+    fn to_param(self) -> f32 {
+        match self {
+            Tonemapper::None => 0.0,
+            Tonemapper::Reinhard => 1.0,
+            Tonemapper::Aces => 2.0,
+        }
+    }
+}
+
+/// A named effect parameter, shown as a slider in the UI (unless the range is a single value)
+struct EffectParam {
+    name: String,
+    range: RangeInclusive<f32>,
+}
+
+/// The extra passes bloom runs before adding the result back onto the image
+struct BloomPasses {
+    extract_pipeline: wgpu::RenderPipeline,
+    blur_horizontal_pipeline: wgpu::RenderPipeline,
+    blur_vertical_pipeline: wgpu::RenderPipeline,
+    /// Half size targets the bright parts are extracted into and blurred between
+    targets: [RenderTarget; 2],
+    /// The parameters for the blur passes, which read the half size targets
+    blur_params_buffer: wgpu::Buffer,
+    /// Read the first and second target, recreated when the targets resize
+    blur_bind_groups: Option<([u64; 2], [wgpu::BindGroup; 2])>,
+}
+
+enum EffectKind {
+    Tonemap,
+    Bloom(Box<BloomPasses>),
+    Fxaa,
+    Vignette,
+    ColorGrading { lut_bind_group: wgpu::BindGroup },
+    Custom,
+}
+
+/// A single fullscreen pass of a `PostProcessStack`
+pub struct PostEffect {
+    pub name: String,
+    /// Disabled effects are skipped
+    pub enabled: bool,
+    kind: EffectKind,
+    pipeline: wgpu::RenderPipeline,
+    values: [f32; MAX_PARAMS],
+    params: Vec<EffectParam>,
+    params_buffer: wgpu::Buffer,
+    /// Reads the input, recreated when the input target changes or resizes
+    input_bind_group: Option<(u64, wgpu::BindGroup)>,
+}
+
+impl PostEffect {
+    /// Scale the brightness by the exposure, then map it to the screen with the tonemapper.
+    /// This should come after any effects that work on HDR colors (e.g. bloom)
+    pub fn tonemap(renderer: &Renderer, tonemapper: Tonemapper, exposure: f32) -> Result<Self> {
+        Self::tonemap_with_format(renderer, Renderer::HDR_FORMAT, tonemapper, exposure)
+    }
+
+    /// A tonemapping effect that writes to a target of any format (e.g. the surface)
+    pub(crate) fn tonemap_with_format(
+        renderer: &Renderer,
+        format: wgpu::TextureFormat,
+        tonemapper: Tonemapper,
+        exposure: f32,
+    ) -> Result<Self> {
+        let mut effect = Self::with_format(
+            renderer,
+            format,
+            "Tonemap",
+            EffectKind::Tonemap,
+            include_str!("shaders/post_tonemap.wgsl"),
+        )?;
+        effect.add_param("Exposure", exposure, 0.0..=8.0);
+        effect.add_param("Tonemapper", tonemapper.to_param(), 0.0..=2.0);
+        Ok(effect)
+    }
+
+    /// Make bright parts of the image glow. Only colors brighter than the threshold bloom, so
+    /// this should come before tonemapping
+    pub fn bloom(renderer: &Renderer, threshold: f32, intensity: f32) -> Result<Self> {
+        let source = include_str!("shaders/post_bloom.wgsl");
+        let layouts = [
+            &*Self::input_bind_group_layout(renderer),
+            &*Self::texture_bind_group_layout(renderer),
+        ];
+
+        let half_size = RenderTargetConfig {
+            size: RenderTargetSize::WindowRelative(0.5),
+            format: Some(Renderer::HDR_FORMAT),
+            depth: false,
+            ..Default::default()
+        };
+        let targets = [
+            renderer.create_render_target(half_size.clone(), "Bloom Target")?,
+            renderer.create_render_target(half_size, "Bloom Target")?,
+        ];
+
+        let mut effect = Self::new(
+            renderer,
+            "Bloom",
+            EffectKind::Bloom(Box::new(BloomPasses {
+                extract_pipeline: Self::create_pipeline(
+                    renderer,
+                    Renderer::HDR_FORMAT,
+                    source,
+                    "fs_extract",
+                    &layouts,
+                )?,
+                blur_horizontal_pipeline: Self::create_pipeline(
+                    renderer,
+                    Renderer::HDR_FORMAT,
+                    source,
+                    "fs_blur_horizontal",
+                    &layouts,
+                )?,
+                blur_vertical_pipeline: Self::create_pipeline(
+                    renderer,
+                    Renderer::HDR_FORMAT,
+                    source,
+                    "fs_blur_vertical",
+                    &layouts,
+                )?,
+                targets,
+                blur_params_buffer: Self::create_params_buffer(renderer, "Bloom Blur"),
+                blur_bind_groups: None,
+            })),
+            source,
+        )?;
+        effect.add_param("Threshold", threshold, 0.0..=4.0);
+        effect.add_param("Intensity", intensity, 0.0..=4.0);
+        effect.add_param("Radius", 1.0, 0.0..=4.0);
+        Ok(effect)
+    }
+
+    /// Fast approximate anti-aliasing, smooths jagged edges. This should come after
+    /// tonemapping
+    pub fn fxaa(renderer: &Renderer) -> Result<Self> {
+        let mut effect = Self::new(
+            renderer,
+            "FXAA",
+            EffectKind::Fxaa,
+            include_str!("shaders/post_fxaa.wgsl"),
+        )?;
+        effect.add_param("Span", 8.0, 1.0..=16.0);
+        effect.add_param("Edge Threshold", 0.05, 0.0..=0.5);
+        Ok(effect)
+    }
+
+    /// Darken the edges of the screen
+    pub fn vignette(renderer: &Renderer, intensity: f32) -> Result<Self> {
+        let mut effect = Self::new(
+            renderer,
+            "Vignette",
+            EffectKind::Vignette,
+            include_str!("shaders/post_vignette.wgsl"),
+        )?;
+        effect.add_param("Intensity", intensity, 0.0..=1.0);
+        effect.add_param("Radius", 0.75, 0.0..=1.5);
+        effect.add_param("Smoothness", 0.45, 0.0..=1.0);
+        Ok(effect)
+    }
+
+    /// Remap colors with a lookup table. The LUT is a strip of `n` slices of `n` by `n`
+    /// pixels (e.g. 256x16), loaded as an sRGB texture with linear filtering. This should come
+    /// after tonemapping
+    pub fn color_grading(renderer: &Renderer, lut: &Texture, strength: f32) -> Result<Self> {
+        let size = lut.size.height;
+        if lut.size.width != size * size {
+            bail!(
+                "A {}x{} LUT should be {} pixels wide",
+                lut.size.width,
+                size,
+                size * size
+            );
+        }
+
+        let (_, lut_bind_group) = BindGroupBuilder::new("LUT Bind Group")
+            .texture(&lut.view, wgpu::ShaderStages::FRAGMENT)
+            .sampler(&lut.sampler, wgpu::ShaderStages::FRAGMENT)
+            .build(renderer);
+
+        let mut effect = Self::new(
+            renderer,
+            "Color Grading",
+            EffectKind::ColorGrading { lut_bind_group },
+            include_str!("shaders/post_color_grading.wgsl"),
+        )?;
+        effect.add_param("Strength", strength, 0.0..=1.0);
+        // The size is fixed by the texture, so it has no range to adjust in the UI
+        effect.add_param("LUT Size", size as f32, size as f32..=size as f32);
+        Ok(effect)
+    }
+
+    /// An effect with a fragment shader written in WGSL. The source is joined onto
+    /// `shaders::POST_PROCESS`, and must define `fs_main` reading from `t_input`. Parameters
+    /// added with `with_param` are read in the shader with `param(index)`
+    pub fn custom(renderer: &Renderer, name: &str, source: &str) -> Result<Self> {
+        Self::new(renderer, name, EffectKind::Custom, source)
+    }
+
+    /// Add a parameter, shown as a slider in the UI. Effects can have up to 8 parameters
+    pub fn with_param(&mut self, name: &str, value: f32, range: RangeInclusive<f32>) -> &mut Self {
+        if self.params.len() == MAX_PARAMS {
+            log::warn!(
+                "Effect '{}' already has {} parameters, '{}' was not added",
+                self.name,
+                MAX_PARAMS,
+                name
+            );
+        } else {
+            self.add_param(name, value, range);
+        }
+        self
+    }
+
+    /// The value of a parameter
+    pub fn param(&self, name: &str) -> Option<f32> {
+        self.params
+            .iter()
+            .position(|param| param.name == name)
+            .map(|index| self.values[index])
+    }
+
+    /// Change a parameter, returning false if the effect has no parameter with this name
+    pub fn set_param(&mut self, name: &str, value: f32) -> bool {
+        match self.params.iter().position(|param| param.name == name) {
+            Some(index) => {
+                self.values[index] = value;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Change the tonemapper of a tonemapping effect
+    pub fn set_tonemapper(&mut self, tonemapper: Tonemapper) {
+        if let EffectKind::Tonemap = self.kind {
+            self.values[1] = tonemapper.to_param();
+        }
+    }
+
+    /// Controls for the effect and its parameters
+    pub fn ui(&mut self, ui: &mut egui::Ui) {
+        ui.checkbox(&mut self.enabled, "Enabled");
+
+        for (index, param) in self.params.iter().enumerate() {
+            // The tonemapper is picked from a list instead of a slider
+            if let (EffectKind::Tonemap, 1) = (&self.kind, index) {
+                let mut tonemapper = Tonemapper::from_param(self.values[index]);
+                ui.horizontal(|ui| {
+                    ui.radio_value(&mut tonemapper, Tonemapper::None, "None");
+                    ui.radio_value(&mut tonemapper, Tonemapper::Reinhard, "Reinhard");
+                    ui.radio_value(&mut tonemapper, Tonemapper::Aces, "ACES");
+                });
+                self.values[index] = tonemapper.to_param();
+                continue;
+            }
+
+            if param.range.start() < param.range.end() {
+                ui.add(
+                    egui::Slider::new(&mut self.values[index], param.range.clone())
+                        .text(&param.name),
+                );
+            }
+        }
+    }
+
+    fn new(renderer: &Renderer, name: &str, kind: EffectKind, source: &str) -> Result<Self> {
+        Self::with_format(renderer, Renderer::HDR_FORMAT, name, kind, source)
+    }
+
+    fn with_format(
+        renderer: &Renderer,
+        format: wgpu::TextureFormat,
+        name: &str,
+        kind: EffectKind,
+        source: &str,
+    ) -> Result<Self> {
+        let mut layouts = vec![Self::input_bind_group_layout(renderer)];
+        if let EffectKind::Bloom(_) | EffectKind::ColorGrading { .. } = kind {
+            layouts.push(Self::texture_bind_group_layout(renderer));
+        }
+        let layouts: Vec<&wgpu::BindGroupLayout> = layouts.iter().map(|l| &**l).collect();
+
+        let pipeline = Self::create_pipeline(renderer, format, source, "fs_main", &layouts)?;
+
+        let params_buffer = Self::create_params_buffer(renderer, name);
+
+        Ok(Self {
+            name: name.to_string(),
+            enabled: true,
+            kind,
+            pipeline,
+            values: [0.0; MAX_PARAMS],
+            params: Vec::new(),
+            params_buffer,
+            input_bind_group: None,
+        })
+    }
+
+    fn create_params_buffer(renderer: &Renderer, name: &str) -> wgpu::Buffer {
+        renderer.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some(name),
+            size: std::mem::size_of::<EffectUniform>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        })
+    }
+
+    fn add_param(&mut self, name: &str, value: f32, range: RangeInclusive<f32>) {
+        self.values[self.params.len()] = value;
+        self.params.push(EffectParam {
+            name: name.to_string(),
+            range,
+        });
+    }
+
+    /// The input texture, its sampler and the effect parameters
+    fn input_bind_group_layout(renderer: &Renderer) -> Arc<wgpu::BindGroupLayout> {
+        renderer.get_bind_group_layout(
+            "Post Effect Input Bind Group Layout",
+            &[
+                Self::texture_layout_entry(0),
+                Self::sampler_layout_entry(1),
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        )
+    }
+
+    /// An extra texture and sampler (e.g. the blurred bloom or a LUT)
+    fn texture_bind_group_layout(renderer: &Renderer) -> Arc<wgpu::BindGroupLayout> {
+        renderer.get_bind_group_layout(
+            "Post Effect Texture Bind Group Layout",
+            &[Self::texture_layout_entry(0), Self::sampler_layout_entry(1)],
+        )
+    }
+
+    fn texture_layout_entry(binding: u32) -> wgpu::BindGroupLayoutEntry {
+        wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                multisampled: false,
+                view_dimension: wgpu::TextureViewDimension::D2,
+                sample_type: wgpu::TextureSampleType::Float { filterable: true },
+            },
+            count: None,
+        }
+    }
+
+    fn sampler_layout_entry(binding: u32) -> wgpu::BindGroupLayoutEntry {
+        wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+            count: None,
+        }
+    }
+
+    fn create_pipeline(
+        renderer: &Renderer,
+        format: wgpu::TextureFormat,
+        source: &str,
+        fragment_shader_entry: &str,
+        layouts: &[&wgpu::BindGroupLayout],
+    ) -> Result<wgpu::RenderPipeline> {
+        let source = format!("{}\n{}", crate::shaders::POST_PROCESS, source);
+
+        RenderPipelineBuilder::new(renderer, "Post Process Pipeline")
+            .with_texture_format(format)
+            .with_sample_count(1)
+            .with_shader_source(wgpu::ShaderSource::Wgsl(source.into()))
+            .with_fragment_shader_entry(fragment_shader_entry)
+            .with_bind_group_layouts(layouts)
+            .with_vertex_buffer_layout(&[])
+            .with_cull_mode(None)
+            .with_blend_mode(BlendMode::Opaque)
+            .with_depth_format(None)
+            .build(&renderer.device)
+    }
+
+    /// Run the effect, reading from the input and writing to the output (which must be the
+    /// format the effect was created for). The sampler must be the same every time, as it is
+    /// only bound when the input changes
+    pub(crate) fn apply(
+        &mut self,
+        encoder: &mut wgpu::CommandEncoder,
+        renderer: &Renderer,
+        sampler: &wgpu::Sampler,
+        input: &RenderTarget,
+        output: &wgpu::TextureView,
+    ) {
+        let params = EffectUniform {
+            values: self.values,
+            texel_size: [
+                1.0 / input.width() as f32,
+                1.0 / input.height() as f32,
+                input.width() as f32,
+                input.height() as f32,
+            ],
+        };
+        renderer
+            .queue
+            .write_buffer(&self.params_buffer, 0, bytemuck::bytes_of(&params));
+
+        let input_bind_group = cached(&mut self.input_bind_group, input.texture_id(), || {
+            Self::create_bind_group(renderer, sampler, input, &self.params_buffer)
+        });
+
+        match &mut self.kind {
+            EffectKind::Bloom(bloom) => {
+                let BloomPasses {
+                    extract_pipeline,
+                    blur_horizontal_pipeline,
+                    blur_vertical_pipeline,
+                    targets,
+                    blur_params_buffer,
+                    blur_bind_groups,
+                } = &mut **bloom;
+
+                for target in targets.iter_mut() {
+                    if let Err(error) = target.resize(renderer) {
+                        log::error!("Failed to resize the bloom targets: {:?}", error);
+                        return;
+                    }
+                }
+
+                // The blur passes read the half size targets, so use their texel size
+                let blur_params = EffectUniform {
+                    texel_size: [
+                        1.0 / targets[0].width() as f32,
+                        1.0 / targets[0].height() as f32,
+                        targets[0].width() as f32,
+                        targets[0].height() as f32,
+                    ],
+                    ..params
+                };
+                renderer.queue.write_buffer(
+                    blur_params_buffer,
+                    0,
+                    bytemuck::bytes_of(&blur_params),
+                );
+
+                let [first, second] = &*targets;
+                let [first_bind_group, second_bind_group] = cached(
+                    blur_bind_groups,
+                    [first.texture_id(), second.texture_id()],
+                    || {
+                        [first, second].map(|target| {
+                            Self::create_bind_group(renderer, sampler, target, blur_params_buffer)
+                        })
+                    },
+                );
+
+                // Extract into the first target, blur across into the second and back
+                draw_fullscreen(
+                    encoder,
+                    extract_pipeline,
+                    &[input_bind_group],
+                    &first.color.view,
+                );
+                draw_fullscreen(
+                    encoder,
+                    blur_horizontal_pipeline,
+                    &[first_bind_group],
+                    &second.color.view,
+                );
+                draw_fullscreen(
+                    encoder,
+                    blur_vertical_pipeline,
+                    &[second_bind_group],
+                    &first.color.view,
+                );
+
+                draw_fullscreen(
+                    encoder,
+                    &self.pipeline,
+                    &[input_bind_group, first.bind_group()],
+                    output,
+                );
+            }
+            EffectKind::ColorGrading { lut_bind_group } => draw_fullscreen(
+                encoder,
+                &self.pipeline,
+                &[input_bind_group, lut_bind_group],
+                output,
+            ),
+            EffectKind::Tonemap | EffectKind::Fxaa | EffectKind::Vignette | EffectKind::Custom => {
+                draw_fullscreen(encoder, &self.pipeline, &[input_bind_group], output)
+            }
+        }
+    }
+
+    /// A bind group reading the input target with the parameters
+    fn create_bind_group(
+        renderer: &Renderer,
+        sampler: &wgpu::Sampler,
+        input: &RenderTarget,
+        params_buffer: &wgpu::Buffer,
+    ) -> wgpu::BindGroup {
+        BindGroupBuilder::new("Post Process Bind Group")
+            .texture(&input.color.view, wgpu::ShaderStages::FRAGMENT)
+            .sampler(sampler, wgpu::ShaderStages::FRAGMENT)
+            .uniform(params_buffer, wgpu::ShaderStages::FRAGMENT)
+            .build(renderer)
+            .1
+    }
+}
+
+/// The cached value, created again if the key (the ids of the textures it reads) has changed
+fn cached<K: PartialEq, V>(cache: &mut Option<(K, V)>, key: K, create: impl FnOnce() -> V) -> &V {
+    if !matches!(cache, Some((cached_key, _)) if *cached_key == key) {
+        *cache = Some((key, create()));
+    }
+
+    &cache.as_ref().unwrap().1
+}
+
+/// The sampler effects read their input with
+pub(crate) fn create_sampler(renderer: &Renderer) -> wgpu::Sampler {
+    renderer.device.create_sampler(&wgpu::SamplerDescriptor {
+        address_mode_u: wgpu::AddressMode::ClampToEdge,
+        address_mode_v: wgpu::AddressMode::ClampToEdge,
+        address_mode_w: wgpu::AddressMode::ClampToEdge,
+        mag_filter: wgpu::FilterMode::Linear,
+        min_filter: wgpu::FilterMode::Linear,
+        ..Default::default()
+    })
+}
+
+/// Draw a fullscreen triangle into the output
+fn draw_fullscreen(
+    encoder: &mut wgpu::CommandEncoder,
+    pipeline: &wgpu::RenderPipeline,
+    bind_groups: &[&wgpu::BindGroup],
+    output: &wgpu::TextureView,
+) {
+    let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+        label: Some("Post Process Render Pass"),
+        color_attachments: &[wgpu::RenderPassColorAttachment {
+            view: output,
+            resolve_target: None,
+            ops: wgpu::Operations {
+                load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                store: true,
+            },
+        }],
+        depth_stencil_attachment: None,
+    });
+
+    render_pass.set_pipeline(pipeline);
+    for (index, bind_group) in bind_groups.iter().enumerate() {
+        render_pass.set_bind_group(index as u32, bind_group, &[]);
+    }
+    render_pass.draw(0..3, 0..1);
+}
+
+/// Effects applied to the scene after it has been rendered. When post processing is enabled
+/// (see `Config::post_processing`) the HDR scene target is passed through each enabled effect
+/// in order before being drawn to the screen, replacing the tonemap pass of `Engine::hdr`
+pub struct PostProcessStack {
+    /// The effects, applied in order
+    pub effects: Vec<PostEffect>,
+    /// Show a window for tweaking the effects
+    pub show_ui: bool,
+    targets: [RenderTarget; 2],
+    sampler: wgpu::Sampler,
+    blitter: Blitter,
+}
+
+impl PostProcessStack {
+    /// Create a stack with no effects
+    pub fn new(renderer: &Renderer) -> Result<Self> {
+        // The targets between effects are HDR, so effects before tonemapping keep the range
+        let config = RenderTargetConfig {
+            format: Some(Renderer::HDR_FORMAT),
+            depth: false,
+            ..Default::default()
+        };
+
+        Ok(Self {
+            effects: Vec::new(),
+            show_ui: false,
+            targets: [
+                renderer.create_render_target(config.clone(), "Post Process Target")?,
+                renderer.create_render_target(config, "Post Process Target")?,
+            ],
+            sampler: create_sampler(renderer),
+            blitter: Blitter::new(renderer.surface_config.format, &renderer.device),
+        })
+    }
+
+    /// Create a stack with bloom, ACES tonemapping, a (disabled) vignette and FXAA
+    pub fn with_default_effects(renderer: &Renderer) -> Result<Self> {
+        let mut stack = Self::new(renderer)?;
+
+        let mut vignette = PostEffect::vignette(renderer, 0.5)?;
+        vignette.enabled = false;
+
+        stack.effects = vec![
+            PostEffect::bloom(renderer, 1.0, 0.5)?,
+            PostEffect::tonemap(renderer, Tonemapper::Aces, 1.0)?,
+            vignette,
+            PostEffect::fxaa(renderer)?,
+        ];
+
+        Ok(stack)
+    }
+
+    /// Add an effect to the end of the stack
+    pub fn push(&mut self, effect: PostEffect) {
+        self.effects.push(effect);
+    }
+
+    /// The first effect with this name
+    pub fn effect_mut(&mut self, name: &str) -> Option<&mut PostEffect> {
+        self.effects.iter_mut().find(|effect| effect.name == name)
+    }
+
+    /// Recreate the targets to match the window size
+    pub fn resize(&mut self, renderer: &Renderer) -> Result<()> {
+        for target in self.targets.iter_mut() {
+            target.resize(renderer)?;
+        }
+
+        Ok(())
+    }
+
+    /// Run the enabled effects over the scene (e.g. `HdrPass::scene_target`), and draw the
+    /// result into the output (which must be the surface format)
+    pub fn apply(
+        &mut self,
+        encoder: &mut wgpu::CommandEncoder,
+        renderer: &Renderer,
+        scene: &RenderTarget,
+        output: &wgpu::TextureView,
+    ) {
+        let Self {
+            effects,
+            targets,
+            sampler,
+            blitter,
+            ..
+        } = self;
+
+        // Swap between the two targets, so each effect reads what the last one wrote
+        let mut input = scene;
+        let mut next = 0;
+        for effect in effects.iter_mut().filter(|effect| effect.enabled) {
+            effect.apply(encoder, renderer, sampler, input, &targets[next].color.view);
+            input = &targets[next];
+            next = 1 - next;
+        }
+
+        blitter.blit(encoder, &input.color, output);
+    }
+
+    /// Controls for every effect
+    pub fn ui(&mut self, ui: &mut egui::Ui) {
+        for (index, effect) in self.effects.iter_mut().enumerate() {
+            egui::CollapsingHeader::new(&effect.name)
+                .id_source(index)
+                .show(ui, |ui| effect.ui(ui));
+        }
+    }
+}
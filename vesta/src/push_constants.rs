@@ -0,0 +1,168 @@
+use std::sync::Arc;
+
+use bytemuck::Pod;
+use crevice::std140::{AsStd140, Std140};
+
+use crate::{Renderer, UniformArena, UniformBufferUtils, UniformSlot};
+
+/// Slots the uniform arena starts with when push constants are emulated
+const EMULATED_INITIAL_SLOTS: u32 = 64;
+
+/// Per-draw data of type `T`, sent as push constants when the device supports them and
+/// emulated with a `UniformArena` otherwise (e.g. on WebGL). Shaders declare the data with
+/// `declaration`, which gives the matching WGSL for whichever path is in use
+pub struct PushConstants<T>
+where
+    T: Pod + AsStd140,
+{
+    stages: wgpu::ShaderStages,
+    emulation: Option<Emulation<T>>,
+}
+
+struct Emulation<T: AsStd140> {
+    arena: UniformArena<T>,
+    layout: Arc<wgpu::BindGroupLayout>,
+    group: u32,
+}
+
+/// The data for a single draw, see `PushConstants::allocate`
+pub struct PushConstantSlot<T> {
+    data: T,
+    uniform_slot: Option<UniformSlot>,
+}
+
+impl<T: Copy> PushConstantSlot<T> {
+    pub fn data(&self) -> T {
+        self.data
+    }
+}
+
+impl Renderer {
+    /// Create per-draw data visible to the shader stages. If push constants are not supported
+    /// the data is bound as a uniform at `fallback_group` instead
+    pub fn create_push_constants<T: Pod + AsStd140>(
+        &self,
+        name: &str,
+        stages: wgpu::ShaderStages,
+        fallback_group: u32,
+    ) -> PushConstants<T> {
+        PushConstants::new(name, stages, fallback_group, self)
+    }
+
+    /// Set push constants from a value using the std140 layout, the same bytes that
+    /// `PushConstants` pushes. The device must support push constants, and the pipeline layout
+    /// must have a range covering `offset` and the std140 size of `T`
+    pub fn set_push_constants<T: AsStd140>(
+        render_pass: &mut wgpu::RenderPass,
+        stages: wgpu::ShaderStages,
+        offset: u32,
+        data: &T,
+    ) {
+        render_pass.set_push_constants(stages, offset, data.as_std140().as_bytes());
+    }
+
+    /// If the device supports push constants of this size
+    pub fn supports_push_constants(&self, size: u32) -> bool {
+        self.device
+            .features()
+            .contains(wgpu::Features::PUSH_CONSTANTS)
+            && size <= self.device.limits().max_push_constant_size
+    }
+}
+
+impl<T: Pod + AsStd140> PushConstants<T> {
+    pub fn new(
+        name: &str,
+        stages: wgpu::ShaderStages,
+        fallback_group: u32,
+        renderer: &Renderer,
+    ) -> Self {
+        let emulation = (!renderer.supports_push_constants(Self::size())).then(|| Emulation {
+            arena: UniformArena::new(name, stages, EMULATED_INITIAL_SLOTS, renderer),
+            layout: renderer.get_bind_group_layout(
+                "uniform_dynamic_bind_group_layout",
+                &[UniformBufferUtils::dynamic_layout_entry(stages)],
+            ),
+            group: fallback_group,
+        });
+
+        Self { stages, emulation }
+    }
+
+    /// The size of the data in bytes, using the std140 layout
+    pub fn size() -> u32 {
+        T::std140_size_static() as u32
+    }
+
+    /// If the data is bound as a uniform instead of being pushed
+    pub fn is_emulated(&self) -> bool {
+        self.emulation.is_some()
+    }
+
+    /// The range to add to the pipeline layout, or `None` when emulated
+    pub fn push_constant_range(&self) -> Option<wgpu::PushConstantRange> {
+        match self.emulation {
+            Some(_) => None,
+            None => Some(wgpu::PushConstantRange {
+                stages: self.stages,
+                range: 0..Self::size(),
+            }),
+        }
+    }
+
+    /// The bind group layout to add to the pipeline layout at the fallback group, or `None`
+    /// when push constants are supported
+    pub fn bind_group_layout(&self) -> Option<&wgpu::BindGroupLayout> {
+        self.emulation
+            .as_ref()
+            .map(|emulation| emulation.layout.as_ref())
+    }
+
+    /// The WGSL declaration of a variable holding the data, join this with the shader source.
+    /// `type_name` is the WGSL struct matching `T`
+    pub fn declaration(&self, name: &str, type_name: &str) -> String {
+        match &self.emulation {
+            Some(emulation) => format!(
+                "[[group({}), binding(0)]]\nvar<uniform> {}: {};\n",
+                emulation.group, name, type_name
+            ),
+            None => format!("var<push_constant> {}: {};\n", name, type_name),
+        }
+    }
+
+    /// Create the data for a draw. When emulated this allocates a slot in the uniform arena,
+    /// which is freed when the returned slot is dropped
+    pub fn allocate(&mut self, data: T, renderer: &Renderer) -> PushConstantSlot<T> {
+        let uniform_slot = self
+            .emulation
+            .as_mut()
+            .map(|emulation| emulation.arena.allocate(&data, renderer));
+
+        PushConstantSlot { data, uniform_slot }
+    }
+
+    /// Change the data for a draw
+    pub fn write(&self, slot: &mut PushConstantSlot<T>, data: T, renderer: &Renderer) {
+        slot.data = data;
+
+        if let (Some(emulation), Some(uniform_slot)) = (&self.emulation, &slot.uniform_slot) {
+            emulation.arena.write(uniform_slot, &data, renderer);
+        }
+    }
+
+    /// Make the data available to the next draw calls
+    pub fn set_push_constants<'a>(
+        &'a self,
+        render_pass: &mut wgpu::RenderPass<'a>,
+        slot: &PushConstantSlot<T>,
+    ) {
+        match (&self.emulation, &slot.uniform_slot) {
+            (Some(emulation), Some(uniform_slot)) => {
+                emulation
+                    .arena
+                    .bind(render_pass, emulation.group, uniform_slot)
+            }
+            _ => render_pass.set_push_constants(self.stages, 0, slot.data.as_std140().as_bytes()),
+        }
+    }
+}
@@ -0,0 +1,169 @@
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::PathBuf;
+
+use anyhow::*;
+use image::codecs::gif::{GifEncoder, Repeat};
+use image::RgbaImage;
+
+/// The output of a recording
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RecordingFormat {
+    /// Every frame saved as a numbered PNG (`frame-00000.png`) inside the recording directory
+    PngSequence,
+    /// An animated GIF. GIF delays are in hundredths of a second, so frame rates that do not
+    /// divide 100 evenly will play back slightly off
+    Gif,
+    /// An animated PNG. Frames are kept in memory until the recording is stopped
+    Apng,
+}
+
+/// Configuration for recording frames, see `Engine::start_recording`
+#[derive(Clone, Debug)]
+pub struct RecordingConfig {
+    /// The directory for a PNG sequence, or the file for GIF and APNG recordings
+    pub path: PathBuf,
+    pub format: RecordingFormat,
+    /// Frames are simulated at exactly this rate while recording, no matter how long they take
+    /// to render
+    pub frame_rate: u32,
+    /// Include the egui layer in recorded frames
+    pub include_ui: bool,
+    /// Stop recording automatically after this many frames
+    pub max_frames: Option<u32>,
+}
+
+impl Default for RecordingConfig {
+    fn default() -> Self {
+        Self {
+            path: PathBuf::from("recording"),
+            format: RecordingFormat::PngSequence,
+            frame_rate: 60,
+            include_ui: false,
+            max_frames: None,
+        }
+    }
+}
+
+enum RecorderOutput {
+    PngSequence,
+    Gif(GifEncoder<BufWriter<File>>),
+    Apng(Vec<RgbaImage>),
+}
+
+/// Encodes captured frames for an active recording
+pub(crate) struct Recorder {
+    pub config: RecordingConfig,
+    output: RecorderOutput,
+    frame_count: u32,
+    frame_size: Option<(u32, u32)>,
+}
+
+impl Recorder {
+    pub fn new(config: RecordingConfig) -> Result<Self> {
+        if config.frame_rate == 0 || config.frame_rate > u16::MAX as u32 {
+            bail!("Invalid recording frame rate {}", config.frame_rate);
+        }
+
+        let output = match config.format {
+            RecordingFormat::PngSequence => {
+                std::fs::create_dir_all(&config.path)?;
+                RecorderOutput::PngSequence
+            }
+            RecordingFormat::Gif => {
+                let mut encoder = GifEncoder::new_with_speed(Self::create_file(&config)?, 10);
+                encoder.set_repeat(Repeat::Infinite)?;
+                RecorderOutput::Gif(encoder)
+            }
+            RecordingFormat::Apng => {
+                // Check the file can be created now, instead of after all the frames are captured
+                Self::create_file(&config)?;
+                RecorderOutput::Apng(Vec::new())
+            }
+        };
+
+        Ok(Self {
+            config,
+            output,
+            frame_count: 0,
+            frame_size: None,
+        })
+    }
+
+    fn create_file(config: &RecordingConfig) -> Result<BufWriter<File>> {
+        if let Some(directory) = config.path.parent() {
+            std::fs::create_dir_all(directory)?;
+        }
+
+        let file = File::create(&config.path)
+            .with_context(|| format!("Failed to create {}", config.path.display()))?;
+
+        Ok(BufWriter::new(file))
+    }
+
+    /// Add the next frame to the recording. Every frame must be the same size
+    pub fn add_frame(&mut self, frame: RgbaImage) -> Result<()> {
+        match self.frame_size {
+            Some(size) if size != frame.dimensions() => bail!(
+                "Recorded frames must all be {}x{}, but got {}x{} (was the window resized?)",
+                size.0,
+                size.1,
+                frame.width(),
+                frame.height()
+            ),
+            _ => self.frame_size = Some(frame.dimensions()),
+        }
+
+        match &mut self.output {
+            RecorderOutput::PngSequence => {
+                let path = self
+                    .config
+                    .path
+                    .join(format!("frame-{:05}.png", self.frame_count));
+                frame.save(&path)?;
+            }
+            RecorderOutput::Gif(encoder) => {
+                let delay = image::Delay::from_numer_denom_ms(1000, self.config.frame_rate);
+                encoder.encode_frame(image::Frame::from_parts(frame, 0, 0, delay))?;
+            }
+            RecorderOutput::Apng(frames) => frames.push(frame),
+        }
+
+        self.frame_count += 1;
+
+        Ok(())
+    }
+
+    /// If the recording has captured `max_frames` frames
+    pub fn is_finished(&self) -> bool {
+        matches!(self.config.max_frames, Some(max_frames) if self.frame_count >= max_frames)
+    }
+
+    /// Finish writing the recording, returning the number of frames recorded
+    pub fn finish(self) -> Result<u32> {
+        match self.output {
+            // Each frame has already been saved
+            RecorderOutput::PngSequence => {}
+            // Dropping the encoder writes the trailer
+            RecorderOutput::Gif(encoder) => drop(encoder),
+            RecorderOutput::Apng(frames) => {
+                if let Some((width, height)) = self.frame_size {
+                    let file = File::create(&self.config.path)?;
+                    let mut encoder = png::Encoder::new(BufWriter::new(file), width, height);
+                    encoder.set_color(png::ColorType::Rgba);
+                    encoder.set_depth(png::BitDepth::Eight);
+                    encoder.set_animated(frames.len() as u32, 0)?;
+                    encoder.set_frame_delay(1, self.config.frame_rate as u16)?;
+
+                    let mut writer = encoder.write_header()?;
+                    for frame in &frames {
+                        writer.write_image_data(frame.as_raw())?;
+                    }
+                    writer.finish()?;
+                }
+            }
+        }
+
+        Ok(self.frame_count)
+    }
+}
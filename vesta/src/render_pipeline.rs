@@ -1,46 +1,150 @@
 use anyhow::*;
 
+use crate::{Renderer, VertexLayout, INSTANCE_BUFFER_SLOT};
+
+/// How the output of a fragment shader is combined with what is already in the color target
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BlendMode {
+    /// Replace the existing color
+    Opaque,
+    /// Blend using the alpha of the output (the default)
+    Alpha,
+    /// Blend output that has already been multiplied by its alpha
+    Premultiplied,
+    /// Add the output (scaled by its alpha) to the existing color, useful for lights and particles
+    Additive,
+    /// Multiply the existing color by the output, useful for tinting and shadows
+    Multiply,
+    Custom(wgpu::BlendState),
+}
+
+impl BlendMode {
+    pub fn blend_state(&self) -> Option<wgpu::BlendState> {
+        match *self {
+            BlendMode::Opaque => None,
+            BlendMode::Alpha => Some(wgpu::BlendState::ALPHA_BLENDING),
+            BlendMode::Premultiplied => Some(wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING),
+            BlendMode::Additive => Some(wgpu::BlendState {
+                color: wgpu::BlendComponent {
+                    src_factor: wgpu::BlendFactor::SrcAlpha,
+                    dst_factor: wgpu::BlendFactor::One,
+                    operation: wgpu::BlendOperation::Add,
+                },
+                alpha: wgpu::BlendComponent {
+                    src_factor: wgpu::BlendFactor::Zero,
+                    dst_factor: wgpu::BlendFactor::One,
+                    operation: wgpu::BlendOperation::Add,
+                },
+            }),
+            BlendMode::Multiply => Some(wgpu::BlendState {
+                color: wgpu::BlendComponent {
+                    src_factor: wgpu::BlendFactor::Dst,
+                    dst_factor: wgpu::BlendFactor::Zero,
+                    operation: wgpu::BlendOperation::Add,
+                },
+                alpha: wgpu::BlendComponent {
+                    src_factor: wgpu::BlendFactor::Zero,
+                    dst_factor: wgpu::BlendFactor::One,
+                    operation: wgpu::BlendOperation::Add,
+                },
+            }),
+            BlendMode::Custom(blend_state) => Some(blend_state),
+        }
+    }
+}
+
 pub struct RenderPipelineBuilder<'a> {
     layout: Option<&'a wgpu::PipelineLayout>,
+    bind_group_layouts: Option<&'a [&'a wgpu::BindGroupLayout]>,
+    push_constant_ranges: Vec<wgpu::PushConstantRange>,
     shader_source: Option<wgpu::ShaderModuleDescriptor<'a>>,
     vertex_shader_entry: &'a str,
     fragment_shader_entry: &'a str,
     texture_format: wgpu::TextureFormat,
+    blend_mode: BlendMode,
+    additional_color_targets: Vec<wgpu::ColorTargetState>,
     pipeline_name: &'a str,
     primitive_topology: wgpu::PrimitiveTopology,
     cull_mode: Option<wgpu::Face>,
     front_face: wgpu::FrontFace,
+    polygon_mode: wgpu::PolygonMode,
     vertex_buffer_layout: Option<&'a [wgpu::VertexBufferLayout<'a>]>,
+    vertex_type_layout: wgpu::VertexBufferLayout<'a>,
+    instance_buffer_layout: Option<wgpu::VertexBufferLayout<'a>>,
     depth_write_enabled: bool,
     depth_compare: wgpu::CompareFunction,
+    depth_format: Option<wgpu::TextureFormat>,
+    depth_bias: wgpu::DepthBiasState,
+    stencil: wgpu::StencilState,
+    sample_count: u32,
 }
 
 impl<'a> RenderPipelineBuilder<'a> {
-    pub fn new(
-        texture_format: wgpu::TextureFormat,
-        pipeline_name: &'a str,
-    ) -> RenderPipelineBuilder {
+    /// Create a builder for a pipeline that draws in the main render pass, matching the
+    /// scene format (the HDR format unless HDR rendering is disabled, see
+    /// `Renderer::scene_format`) and the engine's sample count. Pipelines for other targets
+    /// override these with `with_texture_format` and `with_sample_count`
+    pub fn new(renderer: &Renderer, pipeline_name: &'a str) -> RenderPipelineBuilder<'a> {
         Self {
             layout: None,
+            bind_group_layouts: None,
+            push_constant_ranges: Vec::new(),
             shader_source: None,
             vertex_shader_entry: "vs_main",
             fragment_shader_entry: "fs_main",
-            texture_format,
+            texture_format: renderer.scene_format(),
+            blend_mode: BlendMode::Alpha,
+            additional_color_targets: Vec::new(),
             pipeline_name,
             primitive_topology: wgpu::PrimitiveTopology::TriangleList,
             cull_mode: Some(wgpu::Face::Back),
             front_face: wgpu::FrontFace::Ccw,
+            polygon_mode: wgpu::PolygonMode::Fill,
             vertex_buffer_layout: None,
+            vertex_type_layout: crate::Vertex::layout(),
+            instance_buffer_layout: None,
             depth_write_enabled: true,
             depth_compare: wgpu::CompareFunction::Less,
+            depth_format: Some(crate::texture::Texture::DEPTH_FORMAT),
+            depth_bias: wgpu::DepthBiasState::default(),
+            stencil: wgpu::StencilState::default(),
+            sample_count: renderer.sample_count,
         }
     }
 
+    /// The color format of the target the pipeline draws into
+    pub fn with_texture_format(&mut self, texture_format: wgpu::TextureFormat) -> &mut Self {
+        self.texture_format = texture_format;
+        self
+    }
+
     pub fn with_layout(&mut self, layout: &'a wgpu::PipelineLayout) -> &mut Self {
         self.layout = Some(layout);
         self
     }
 
+    /// Create the pipeline layout from these bind group layouts (and any push constant
+    /// ranges) instead of supplying one with `with_layout`
+    pub fn with_bind_group_layouts(
+        &mut self,
+        bind_group_layouts: &'a [&'a wgpu::BindGroupLayout],
+    ) -> &mut Self {
+        self.bind_group_layouts = Some(bind_group_layouts);
+        self
+    }
+
+    /// Add a push constant range to the pipeline layout, this requires the `PUSH_CONSTANTS`
+    /// device feature. See `PushConstants::push_constant_range`
+    pub fn with_push_constant_range(
+        &mut self,
+        stages: wgpu::ShaderStages,
+        range: std::ops::Range<u32>,
+    ) -> &mut Self {
+        self.push_constant_ranges
+            .push(wgpu::PushConstantRange { stages, range });
+        self
+    }
+
     pub fn with_shader_source(&mut self, source: wgpu::ShaderSource<'a>) -> &mut Self {
         self.shader_source = Some(wgpu::ShaderModuleDescriptor {
             label: None,
@@ -77,6 +181,40 @@ impl<'a> RenderPipelineBuilder<'a> {
         self
     }
 
+    /// Draw triangles filled (the default), as lines (wireframe) or as points. Lines and points
+    /// require the `POLYGON_MODE_LINE` and `POLYGON_MODE_POINT` device features
+    pub fn with_polygon_mode(&mut self, polygon_mode: wgpu::PolygonMode) -> &mut Self {
+        self.polygon_mode = polygon_mode;
+        self
+    }
+
+    /// How the output is blended into the first color target, defaults to `BlendMode::Alpha`
+    pub fn with_blend_mode(&mut self, blend_mode: BlendMode) -> &mut Self {
+        self.blend_mode = blend_mode;
+        self
+    }
+
+    /// Add another color target after the first, written to by `[[location(n)]]` outputs of
+    /// the fragment shader in the order they are added
+    pub fn add_color_target(
+        &mut self,
+        format: wgpu::TextureFormat,
+        blend_mode: BlendMode,
+    ) -> &mut Self {
+        self.additional_color_targets.push(wgpu::ColorTargetState {
+            format,
+            blend: blend_mode.blend_state(),
+            write_mask: wgpu::ColorWrites::ALL,
+        });
+        self
+    }
+
+    /// The number of samples per pixel, this must match the render pass attachments
+    pub fn with_sample_count(&mut self, sample_count: u32) -> &mut Self {
+        self.sample_count = sample_count;
+        self
+    }
+
     #[allow(dead_code)]
     pub fn with_vertex_buffer_layout(
         &mut self,
@@ -86,6 +224,29 @@ impl<'a> RenderPipelineBuilder<'a> {
         self
     }
 
+    /// Set the vertex type used by meshes drawn with this pipeline (defaults to `Vertex`). This
+    /// is ignored if vertex buffer layouts are provided directly
+    pub fn with_vertex_type<V: VertexLayout>(&mut self) -> &mut Self {
+        self.vertex_type_layout = V::layout();
+        self
+    }
+
+    /// Set a per-instance buffer layout, instance data is read from `INSTANCE_BUFFER_SLOT`
+    /// right after the mesh's vertex buffer. Instanced pipelines must have a single vertex
+    /// buffer layout
+    pub fn with_instance_buffer_layout(
+        &mut self,
+        layout: wgpu::VertexBufferLayout<'a>,
+    ) -> &mut Self {
+        self.instance_buffer_layout = Some(layout);
+        self
+    }
+
+    /// Set the per-instance data type (e.g. `InstanceData`) used with this pipeline
+    pub fn with_instance_type<I: VertexLayout>(&mut self) -> &mut Self {
+        self.with_instance_buffer_layout(I::layout())
+    }
+
     #[allow(dead_code)]
     pub fn with_depth_write_enabled(&mut self, depth_write_enabled: bool) -> &mut Self {
         self.depth_write_enabled = depth_write_enabled;
@@ -98,18 +259,111 @@ impl<'a> RenderPipelineBuilder<'a> {
         self
     }
 
+    /// The format of the depth attachment, or `None` for pipelines used in render passes
+    /// without depth. Defaults to `Texture::DEPTH_FORMAT`
+    pub fn with_depth_format(&mut self, depth_format: Option<wgpu::TextureFormat>) -> &mut Self {
+        self.depth_format = depth_format;
+        self
+    }
+
+    /// Offset the depth of drawn primitives, e.g. to stop decals and shadow maps from
+    /// z-fighting
+    pub fn with_depth_bias(&mut self, depth_bias: wgpu::DepthBiasState) -> &mut Self {
+        self.depth_bias = depth_bias;
+        self
+    }
+
+    /// Enable stencil testing, this requires a depth format with a stencil aspect (e.g.
+    /// `Texture::DEPTH_STENCIL_FORMAT`)
+    pub fn with_stencil(&mut self, stencil: wgpu::StencilState) -> &mut Self {
+        self.stencil = stencil;
+        self
+    }
+
     pub fn build(&mut self, device: &wgpu::Device) -> Result<wgpu::RenderPipeline> {
-        // Ensure layout
-        if self.layout.is_none() {
-            bail!("No pipeline layout was supplied!");
+        if !self.push_constant_ranges.is_empty() {
+            if self.layout.is_some() {
+                bail!(
+                    "Push constant ranges are part of the pipeline layout, supply bind group \
+                     layouts with with_bind_group_layouts instead of using with_layout"
+                );
+            }
+
+            if !device.features().contains(wgpu::Features::PUSH_CONSTANTS) {
+                bail!("Push constants are not supported by this device");
+            }
+
+            let max_size = device.limits().max_push_constant_size;
+            if let Some(range) = self
+                .push_constant_ranges
+                .iter()
+                .find(|r| r.range.end > max_size)
+            {
+                bail!(
+                    "Push constant range {:?} is larger than the device limit of {} bytes",
+                    range.range,
+                    max_size
+                );
+            }
         }
-        let layout = self.layout.unwrap();
+
+        // Ensure layout, creating it from the bind group layouts if needed
+        let created_layout = match (self.layout, self.bind_group_layouts) {
+            (Some(_), _) => None,
+            (None, Some(bind_group_layouts)) => Some(device.create_pipeline_layout(
+                &wgpu::PipelineLayoutDescriptor {
+                    label: Some(self.pipeline_name),
+                    bind_group_layouts,
+                    push_constant_ranges: &self.push_constant_ranges,
+                },
+            )),
+            (None, None) => bail!("No pipeline layout was supplied!"),
+        };
+        let layout = self.layout.or(created_layout.as_ref()).unwrap();
 
         // Ensure shader source
         if self.shader_source.is_none() {
             bail!("No shader source supplied!");
         }
 
+        let required_feature = match self.polygon_mode {
+            wgpu::PolygonMode::Fill => wgpu::Features::empty(),
+            wgpu::PolygonMode::Line => wgpu::Features::POLYGON_MODE_LINE,
+            wgpu::PolygonMode::Point => wgpu::Features::POLYGON_MODE_POINT,
+        };
+        if !device.features().contains(required_feature) {
+            bail!(
+                "{:?} polygon mode is not supported by this device",
+                self.polygon_mode
+            );
+        }
+
+        let depth_stencil = match self.depth_format {
+            Some(format) => {
+                if self.stencil.is_enabled()
+                    && !matches!(format, wgpu::TextureFormat::Depth24PlusStencil8)
+                {
+                    bail!("Depth format {:?} has no stencil aspect", format);
+                }
+
+                Some(wgpu::DepthStencilState {
+                    format,
+                    depth_write_enabled: self.depth_write_enabled,
+                    depth_compare: self.depth_compare,
+                    stencil: self.stencil.clone(),
+                    bias: self.depth_bias,
+                })
+            }
+            None => None,
+        };
+
+        let mut targets = vec![wgpu::ColorTargetState {
+            format: self.texture_format,
+            blend: self.blend_mode.blend_state(),
+            write_mask: wgpu::ColorWrites::ALL,
+        }];
+        targets.extend(self.additional_color_targets.iter().cloned());
+
         // Create the module
         let shader_module = device.create_shader_module(
             &self
@@ -118,12 +372,23 @@ impl<'a> RenderPipelineBuilder<'a> {
                 .context("No shader source supplied!")?,
         );
 
-        // I really don't like this, but I don't know how to do this correctly in rust,
-        // may need to rewrite this entire class...
-        let mut buffers: &[wgpu::VertexBufferLayout] = &[crate::Vertex::layout()];
-        if self.vertex_buffer_layout.is_some() {
-            // Use the provided
-            buffers = self.vertex_buffer_layout.unwrap()
+        // Use the provided vertex layouts, or fall back to the vertex type, followed
+        // by the instance layout (if any)
+        let mut buffers: Vec<wgpu::VertexBufferLayout> = match self.vertex_buffer_layout {
+            Some(layouts) => layouts.to_vec(),
+            None => vec![self.vertex_type_layout.clone()],
+        };
+        if let Some(instance_buffer_layout) = &self.instance_buffer_layout {
+            if buffers.len() != INSTANCE_BUFFER_SLOT as usize {
+                bail!(
+                    "Instanced pipelines read instances from vertex buffer slot {}, so they must \
+                     have a single vertex buffer layout (found {})",
+                    INSTANCE_BUFFER_SLOT,
+                    buffers.len()
+                );
+            }
+
+            buffers.push(instance_buffer_layout.clone());
         }
 
         // Create the actual pipeline
@@ -133,48 +398,27 @@ impl<'a> RenderPipelineBuilder<'a> {
             vertex: wgpu::VertexState {
                 module: &shader_module,
                 entry_point: self.vertex_shader_entry,
-                buffers,
+                buffers: &buffers,
             },
             primitive: wgpu::PrimitiveState {
                 topology: self.primitive_topology,
                 strip_index_format: None,
                 front_face: self.front_face,
                 cull_mode: self.cull_mode,
-                polygon_mode: wgpu::PolygonMode::Fill,
+                polygon_mode: self.polygon_mode,
                 unclipped_depth: false,
                 conservative: false,
             },
-            depth_stencil: Some(wgpu::DepthStencilState {
-                format: crate::texture::Texture::DEPTH_FORMAT,
-                depth_write_enabled: self.depth_write_enabled,
-                depth_compare: self.depth_compare,
-                stencil: wgpu::StencilState::default(),
-                bias: wgpu::DepthBiasState::default(),
-            }),
+            depth_stencil,
             multisample: wgpu::MultisampleState {
-                count: 1,
+                count: self.sample_count,
                 mask: !0,
                 alpha_to_coverage_enabled: false,
             },
             fragment: Some(wgpu::FragmentState {
                 module: &shader_module,
                 entry_point: self.fragment_shader_entry,
-                targets: &[wgpu::ColorTargetState {
-                    format: self.texture_format,
-                    blend: Some(wgpu::BlendState {
-                        color: wgpu::BlendComponent {
-                            src_factor: wgpu::BlendFactor::SrcAlpha,
-                            dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
-                            operation: wgpu::BlendOperation::Add,
-                        },
-                        alpha: wgpu::BlendComponent {
-                            src_factor: wgpu::BlendFactor::One,
-                            dst_factor: wgpu::BlendFactor::One,
-                            operation: wgpu::BlendOperation::Add,
-                        },
-                    }),
-                    write_mask: wgpu::ColorWrites::ALL,
-                }],
+                targets: &targets,
             }),
             multiview: None,
         });
@@ -0,0 +1,389 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use anyhow::*;
+
+use crate::{Renderer, Texture};
+
+/// How the size of a render target is determined
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RenderTargetSize {
+    /// A fixed size in pixels (e.g. a minimap or shadow map)
+    Fixed(u32, u32),
+    /// A scale of the window size, 1.0 is the same size as the window
+    WindowRelative(f32),
+}
+
+impl RenderTargetSize {
+    /// Calculate the size in pixels for the current window size
+    pub fn calculate(&self, window_width: u32, window_height: u32) -> (u32, u32) {
+        match *self {
+            RenderTargetSize::Fixed(width, height) => (width.max(1), height.max(1)),
+            RenderTargetSize::WindowRelative(scale) => (
+                ((window_width as f32 * scale).round() as u32).max(1),
+                ((window_height as f32 * scale).round() as u32).max(1),
+            ),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct RenderTargetConfig {
+    pub size: RenderTargetSize,
+    /// The color format, defaults to the format of the main render pass (see
+    /// `Renderer::scene_format`)
+    pub format: Option<wgpu::TextureFormat>,
+    /// Multisampled targets are resolved into the color texture at the end of each render pass
+    pub sample_count: u32,
+    /// Create a depth texture for this target
+    pub depth: bool,
+    /// The color this target is cleared to by `begin_render_pass`
+    pub clear_color: wgpu::Color,
+    pub sampler_address_mode: wgpu::AddressMode,
+    pub sampler_filter: wgpu::FilterMode,
+}
+
+impl Default for RenderTargetConfig {
+    fn default() -> Self {
+        Self {
+            size: RenderTargetSize::WindowRelative(1.0),
+            format: None,
+            sample_count: 1,
+            depth: true,
+            clear_color: wgpu::Color::BLACK,
+            sampler_address_mode: wgpu::AddressMode::ClampToEdge,
+            sampler_filter: wgpu::FilterMode::Linear,
+        }
+    }
+}
+
+/// An offscreen texture that can be rendered into, and then sampled by other shaders.
+///
+/// Window relative targets are only resized when a render pass is begun on them (see
+/// `begin_render_pass`), not when the window itself is resized. Targets that are sampled
+/// without being rendered into first must be resized with `resize` when the window size
+/// changes (e.g. from `VestaApp::resize`)
+pub struct RenderTarget {
+    /// The rendered color output, this is what gets bound to shaders
+    pub color: Texture,
+    /// The depth output (if enabled)
+    pub depth: Option<Texture>,
+    /// Rendered into instead of `color` when multisampled, and resolved into `color`
+    multisampled_color: Option<wgpu::TextureView>,
+    config: RenderTargetConfig,
+    format: wgpu::TextureFormat,
+    label: String,
+    width: u32,
+    height: u32,
+    texture_id: u64,
+}
+
+impl Renderer {
+    /// Create a new offscreen render target
+    pub fn create_render_target(
+        &self,
+        config: RenderTargetConfig,
+        label: &str,
+    ) -> Result<RenderTarget> {
+        RenderTarget::new(config, label, self)
+    }
+}
+
+impl RenderTarget {
+    pub fn new(config: RenderTargetConfig, label: &str, renderer: &Renderer) -> Result<Self> {
+        if !config.sample_count.is_power_of_two() {
+            bail!(
+                "Render target sample count must be a power of two, not {}",
+                config.sample_count
+            );
+        }
+
+        let format = config.format.unwrap_or(renderer.scene_format());
+        let (width, height) = config.size.calculate(
+            renderer.surface_config.width,
+            renderer.surface_config.height,
+        );
+
+        let (color, multisampled_color, depth) =
+            Self::create_textures(&config, format, label, width, height, renderer)?;
+
+        Ok(Self {
+            color,
+            depth,
+            multisampled_color,
+            config,
+            format,
+            label: label.to_string(),
+            width,
+            height,
+            texture_id: next_texture_id(),
+        })
+    }
+
+    fn create_textures(
+        config: &RenderTargetConfig,
+        format: wgpu::TextureFormat,
+        label: &str,
+        width: u32,
+        height: u32,
+        renderer: &Renderer,
+    ) -> Result<(Texture, Option<wgpu::TextureView>, Option<Texture>)> {
+        let device = &renderer.device;
+        let size = wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        };
+
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some(label),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                | wgpu::TextureUsages::TEXTURE_BINDING
+                | wgpu::TextureUsages::COPY_SRC,
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        // Formats that can't be filtered (e.g. `R32Float`) must use a nearest sampler
+        let filter = if Self::is_filterable(format) {
+            config.sampler_filter
+        } else {
+            wgpu::FilterMode::Nearest
+        };
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: config.sampler_address_mode,
+            address_mode_v: config.sampler_address_mode,
+            address_mode_w: config.sampler_address_mode,
+            mag_filter: filter,
+            min_filter: filter,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &Self::create_bind_group_layout(format, renderer),
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&sampler),
+                },
+            ],
+            label: Some("render_target_bind_group"),
+        });
+
+        let color = Texture {
+            texture,
+            view,
+            sampler,
+            bind_group: Some(bind_group),
+            size,
+            format,
+        };
+
+        let multisampled_color = (config.sample_count > 1).then(|| {
+            device
+                .create_texture(&wgpu::TextureDescriptor {
+                    label: Some(label),
+                    size,
+                    mip_level_count: 1,
+                    sample_count: config.sample_count,
+                    dimension: wgpu::TextureDimension::D2,
+                    format,
+                    usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+                })
+                .create_view(&wgpu::TextureViewDescriptor::default())
+        });
+
+        let depth = if config.depth {
+            Some(Texture::create_depth_with_size(
+                device,
+                width,
+                height,
+                config.sample_count,
+                Some(label),
+            )?)
+        } else {
+            None
+        };
+
+        Ok((color, multisampled_color, depth))
+    }
+
+    /// Recreate the textures if the window size has changed, returns true if they were
+    /// recreated. Bind groups on the textures are recreated too, but any other bind groups
+    /// referencing them need to be recreated by the caller. This must be called when the
+    /// window is resized for window relative targets that are not rendered into each frame
+    pub fn resize(&mut self, renderer: &Renderer) -> Result<bool> {
+        let (width, height) = self.config.size.calculate(
+            renderer.surface_config.width,
+            renderer.surface_config.height,
+        );
+
+        if (width, height) == (self.width, self.height) {
+            return Ok(false);
+        }
+
+        let (color, multisampled_color, depth) = Self::create_textures(
+            &self.config,
+            self.format,
+            &self.label,
+            width,
+            height,
+            renderer,
+        )?;
+
+        self.color = color;
+        self.multisampled_color = multisampled_color;
+        self.depth = depth;
+        self.width = width;
+        self.height = height;
+        self.texture_id = next_texture_id();
+
+        Ok(true)
+    }
+
+    /// The color attachment for a render pass drawing into this target
+    pub fn color_attachment(
+        &self,
+        load: wgpu::LoadOp<wgpu::Color>,
+    ) -> wgpu::RenderPassColorAttachment<'_> {
+        match &self.multisampled_color {
+            Some(multisampled_color) => wgpu::RenderPassColorAttachment {
+                view: multisampled_color,
+                resolve_target: Some(&self.color.view),
+                ops: wgpu::Operations { load, store: true },
+            },
+            None => wgpu::RenderPassColorAttachment {
+                view: &self.color.view,
+                resolve_target: None,
+                ops: wgpu::Operations { load, store: true },
+            },
+        }
+    }
+
+    /// The depth attachment for a render pass drawing into this target (if it has depth)
+    pub fn depth_stencil_attachment(
+        &self,
+        load: wgpu::LoadOp<f32>,
+    ) -> Option<wgpu::RenderPassDepthStencilAttachment<'_>> {
+        self.depth
+            .as_ref()
+            .map(|depth| wgpu::RenderPassDepthStencilAttachment {
+                view: &depth.view,
+                depth_ops: Some(wgpu::Operations { load, store: true }),
+                stencil_ops: None,
+            })
+    }
+
+    /// Begin a render pass that clears and draws into this target. Window relative targets are
+    /// resized first if the window size has changed, so bind groups referencing the textures
+    /// should be created after this (e.g. each frame)
+    pub fn begin_render_pass<'a>(
+        &'a mut self,
+        encoder: &'a mut wgpu::CommandEncoder,
+        renderer: &Renderer,
+    ) -> wgpu::RenderPass<'a> {
+        if let Err(error) = self.resize(renderer) {
+            log::error!(
+                "Failed to resize render target '{}': {:?}",
+                self.label,
+                error
+            );
+        }
+
+        encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some(&self.label),
+            color_attachments: &[
+                self.color_attachment(wgpu::LoadOp::Clear(self.config.clear_color))
+            ],
+            depth_stencil_attachment: self.depth_stencil_attachment(wgpu::LoadOp::Clear(1.0)),
+        })
+    }
+
+    /// Copy the color output back to the CPU, blocking until the GPU has finished
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn read_to_image(&self, renderer: &Renderer) -> Result<image::RgbaImage> {
+        self.color.read_to_image(&renderer.device, &renderer.queue)
+    }
+
+    /// Copy the color output back to the CPU, see `Texture::read_to_image_async`
+    pub async fn read_to_image_async(&self, renderer: &Renderer) -> Result<image::RgbaImage> {
+        self.color
+            .read_to_image_async(&renderer.device, &renderer.queue)
+            .await
+    }
+
+    /// Copy the depth output back to the CPU as a grayscale image (if the target has single
+    /// sampled depth), blocking until the GPU has finished
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn read_depth_to_image(&self, renderer: &Renderer) -> Result<image::RgbaImage> {
+        match &self.depth {
+            Some(depth) if self.config.sample_count == 1 => {
+                depth.read_to_image(&renderer.device, &renderer.queue)
+            }
+            Some(_) => bail!("Multisampled depth cannot be read back to the CPU"),
+            None => bail!("Render target '{}' has no depth", self.label),
+        }
+    }
+
+    /// The bind group used to sample the color output, see `bind_group_layout`
+    pub fn bind_group(&self) -> &wgpu::BindGroup {
+        self.color.bind_group.as_ref().unwrap()
+    }
+
+    /// The layout of `bind_group`. This matches `Texture::create_bind_group_layout`, unless the
+    /// format can't be filtered (e.g. `R32Float`), which needs a non-filtering sampler
+    pub fn bind_group_layout(&self, renderer: &Renderer) -> Arc<wgpu::BindGroupLayout> {
+        Self::create_bind_group_layout(self.format, renderer)
+    }
+
+    fn create_bind_group_layout(
+        format: wgpu::TextureFormat,
+        renderer: &Renderer,
+    ) -> Arc<wgpu::BindGroupLayout> {
+        renderer.get_bind_group_layout(
+            "texture_bind_group_layout",
+            &Texture::layout_entries(wgpu::TextureViewDimension::D2, Self::is_filterable(format)),
+        )
+    }
+
+    fn is_filterable(format: wgpu::TextureFormat) -> bool {
+        format.describe().guaranteed_format_features.filterable
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn format(&self) -> wgpu::TextureFormat {
+        self.format
+    }
+
+    pub fn sample_count(&self) -> u32 {
+        self.config.sample_count
+    }
+
+    /// Identifies the current textures, this changes whenever they are recreated so bind
+    /// groups referencing them know to be recreated too
+    pub fn texture_id(&self) -> u64 {
+        self.texture_id
+    }
+}
+
+/// A unique id for each set of render target textures
+fn next_texture_id() -> u64 {
+    static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+    NEXT_ID.fetch_add(1, Ordering::Relaxed)
+}
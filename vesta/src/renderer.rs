@@ -1,7 +1,81 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
 pub struct Renderer {
     pub surface: wgpu::Surface,
     pub device: wgpu::Device,
     pub queue: wgpu::Queue,
     pub surface_config: wgpu::SurfaceConfiguration,
     pub depth_texture: crate::texture::Texture,
+    /// Samples per pixel of the main render pass, pipelines drawing in it must match this
+    pub sample_count: u32,
+    /// If the main render pass draws into the HDR scene target, see `hdr_format`
+    pub(crate) hdr: bool,
+    /// Drawn into by the main render pass when multisampling, and resolved to the frame
+    pub(crate) multisampled_framebuffer: Option<wgpu::TextureView>,
+    /// Bind group layouts keyed by their entries, see `Renderer::get_bind_group_layout`
+    pub(crate) bind_group_layouts:
+        Mutex<HashMap<Vec<wgpu::BindGroupLayoutEntry>, Arc<wgpu::BindGroupLayout>>>,
+    /// The GPU being rendered with, see `DeviceConfig`
+    pub adapter_info: wgpu::AdapterInfo,
+}
+
+impl Renderer {
+    /// The format of HDR render targets, which can hold colors brighter than 1.0
+    pub const HDR_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+
+    /// The format of the scene target the main render pass draws into, or `None` if HDR
+    /// rendering is disabled and the main render pass draws straight to the surface (see
+    /// `HdrConfig`)
+    pub fn hdr_format(&self) -> Option<wgpu::TextureFormat> {
+        if self.hdr {
+            Some(Self::HDR_FORMAT)
+        } else {
+            None
+        }
+    }
+
+    /// The color format pipelines drawing in the main render pass must use, this is the HDR
+    /// format when HDR rendering is enabled and the surface format otherwise
+    pub fn scene_format(&self) -> wgpu::TextureFormat {
+        self.hdr_format().unwrap_or(self.surface_config.format)
+    }
+
+    /// The features enabled on the device, this includes any optional features from the
+    /// `DeviceConfig` that the adapter supports
+    pub fn features(&self) -> wgpu::Features {
+        self.device.features()
+    }
+
+    /// The limits of the device
+    pub fn limits(&self) -> wgpu::Limits {
+        self.device.limits()
+    }
+
+    /// Create the multisampled color texture the main render pass draws into (if
+    /// multisampling is enabled)
+    pub(crate) fn create_multisampled_framebuffer(
+        device: &wgpu::Device,
+        surface_config: &wgpu::SurfaceConfiguration,
+        format: wgpu::TextureFormat,
+        sample_count: u32,
+    ) -> Option<wgpu::TextureView> {
+        (sample_count > 1).then(|| {
+            device
+                .create_texture(&wgpu::TextureDescriptor {
+                    label: Some("Multisampled Framebuffer"),
+                    size: wgpu::Extent3d {
+                        width: surface_config.width.max(1),
+                        height: surface_config.height.max(1),
+                        depth_or_array_layers: 1,
+                    },
+                    mip_level_count: 1,
+                    sample_count,
+                    dimension: wgpu::TextureDimension::D2,
+                    format,
+                    usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+                })
+                .create_view(&wgpu::TextureViewDescriptor::default())
+        })
+    }
 }
@@ -0,0 +1,12 @@
+//! WGSL sources for shader helpers, join these with your own shader source
+
+/// Helpers for sampling 2D texture arrays, see `Texture::from_images_array`
+pub const TEXTURE_ARRAY: &str = include_str!("shaders/texture_array.wgsl");
+
+/// Helpers for sampling cubemaps as environment maps (reflections and refractions), see
+/// `Texture::from_cubemap`
+pub const ENVIRONMENT: &str = include_str!("shaders/environment.wgsl");
+
+/// The vertex shader, bindings and helpers shared by post processing effects. This is joined
+/// onto the source of custom effects automatically, see `PostEffect::custom`
+pub const POST_PROCESS: &str = include_str!("shaders/post_process.wgsl");
@@ -0,0 +1,31 @@
+// Helpers for using cubemaps (see `Texture::from_cubemap`) as environment maps.
+// Join this source with your shader, for example:
+//
+// [[group(3), binding(0)]]
+// var u_environment: texture_cube<f32>;
+// [[group(3), binding(1)]]
+// var u_environment_sampler: sampler;
+//
+// let view_direction = in.world_position - u_camera.view_pos.xyz;
+// let reflection = sample_environment_reflection(u_environment, u_environment_sampler, view_direction, in.normal);
+
+// Sample the environment in the direction light is reflected off a surface
+fn sample_environment_reflection(t: texture_cube<f32>, s: sampler, view_direction: vec3<f32>, normal: vec3<f32>) -> vec4<f32> {
+    return textureSample(t, s, reflect(normalize(view_direction), normalize(normal)));
+}
+
+// Sample the environment in the direction light is refracted through a surface, `ratio` is
+// the ratio of the indices of refraction (e.g. 1.0 / 1.33 from air into water)
+fn sample_environment_refraction(t: texture_cube<f32>, s: sampler, view_direction: vec3<f32>, normal: vec3<f32>, ratio: f32) -> vec4<f32> {
+    let i = normalize(view_direction);
+    let n = normalize(normal);
+
+    // Same as the GLSL refract builtin, with total internal reflection falling back
+    // to a reflection
+    let cos_i = dot(n, i);
+    let k = 1.0 - ratio * ratio * (1.0 - cos_i * cos_i);
+    let refracted = ratio * i - (ratio * cos_i + sqrt(max(k, 0.0))) * n;
+    let direction = select(refracted, reflect(i, n), k < 0.0);
+
+    return textureSample(t, s, direction);
+}
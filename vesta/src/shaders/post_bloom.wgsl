@@ -0,0 +1,46 @@
+// param(0): threshold, param(1): intensity, param(2): radius (in pixels of the half size blur)
+
+[[group(1), binding(0)]]
+var t_bloom: texture_2d<f32>;
+
+[[group(1), binding(1)]]
+var s_bloom: sampler;
+
+// Keep only the bright parts of the image, with a soft falloff around the threshold
+[[stage(fragment)]]
+fn fs_extract(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+    let color = textureSample(t_input, s_input, in.tex_coords).rgb;
+    let brightness = max(color.r, max(color.g, color.b));
+    let contribution = max(brightness - param(0u), 0.0) / max(brightness, 0.0001);
+    return vec4<f32>(color * contribution, 1.0);
+}
+
+// A 9 tap gaussian blur along one axis, using linear filtering to read two texels per tap
+fn blur(tex_coords: vec2<f32>, direction: vec2<f32>) -> vec4<f32> {
+    let step = direction * texel_size() * param(2u);
+
+    var color = textureSample(t_input, s_input, tex_coords).rgb * 0.2270270270;
+    color = color + textureSample(t_input, s_input, tex_coords + step * 1.3846153846).rgb * 0.3162162162;
+    color = color + textureSample(t_input, s_input, tex_coords - step * 1.3846153846).rgb * 0.3162162162;
+    color = color + textureSample(t_input, s_input, tex_coords + step * 3.2307692308).rgb * 0.0702702703;
+    color = color + textureSample(t_input, s_input, tex_coords - step * 3.2307692308).rgb * 0.0702702703;
+    return vec4<f32>(color, 1.0);
+}
+
+[[stage(fragment)]]
+fn fs_blur_horizontal(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+    return blur(in.tex_coords, vec2<f32>(1.0, 0.0));
+}
+
+[[stage(fragment)]]
+fn fs_blur_vertical(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+    return blur(in.tex_coords, vec2<f32>(0.0, 1.0));
+}
+
+// Add the blurred bright parts back onto the image
+[[stage(fragment)]]
+fn fs_main(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+    let input = textureSample(t_input, s_input, in.tex_coords);
+    let bloom = textureSample(t_bloom, s_bloom, in.tex_coords).rgb;
+    return vec4<f32>(input.rgb + bloom * param(1u), input.a);
+}
@@ -0,0 +1,34 @@
+// param(0): strength, param(1): LUT size (the number of blue slices)
+// The LUT is a strip of slices, one per blue value, with red increasing to the right and
+// green increasing downwards in each slice. This expects tonemapped (0 to 1) input
+
+[[group(1), binding(0)]]
+var t_lut: texture_2d<f32>;
+
+[[group(1), binding(1)]]
+var s_lut: sampler;
+
+fn lut_coords(color: vec3<f32>, slice: f32, size: f32) -> vec2<f32> {
+    let texel = (color.rg * (size - 1.0) + 0.5) / vec2<f32>(size * size, size);
+    return vec2<f32>(texel.x + slice / size, texel.y);
+}
+
+[[stage(fragment)]]
+fn fs_main(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+    let input = textureSample(t_input, s_input, in.tex_coords);
+    let size = param(1u);
+
+    // LUTs are authored for gamma encoded colors
+    let color = pow(clamp(input.rgb, vec3<f32>(0.0), vec3<f32>(1.0)), vec3<f32>(1.0 / 2.2));
+
+    // Blend between the two nearest blue slices
+    let blue = color.b * (size - 1.0);
+    let slice = floor(blue);
+    let graded = mix(
+        textureSample(t_lut, s_lut, lut_coords(color, slice, size)).rgb,
+        textureSample(t_lut, s_lut, lut_coords(color, min(slice + 1.0, size - 1.0), size)).rgb,
+        blue - slice
+    );
+
+    return vec4<f32>(mix(input.rgb, graded, param(0u)), input.a);
+}
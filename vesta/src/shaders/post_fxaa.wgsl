@@ -0,0 +1,51 @@
+// param(0): maximum search span in pixels, param(1): edge threshold
+// This expects tonemapped (0 to 1) input
+
+fn fxaa_luma(tex_coords: vec2<f32>) -> f32 {
+    return luminance(textureSample(t_input, s_input, tex_coords).rgb);
+}
+
+[[stage(fragment)]]
+fn fs_main(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+    let texel = texel_size();
+    let center = textureSample(t_input, s_input, in.tex_coords);
+
+    let luma_nw = fxaa_luma(in.tex_coords + vec2<f32>(-1.0, -1.0) * texel);
+    let luma_ne = fxaa_luma(in.tex_coords + vec2<f32>(1.0, -1.0) * texel);
+    let luma_sw = fxaa_luma(in.tex_coords + vec2<f32>(-1.0, 1.0) * texel);
+    let luma_se = fxaa_luma(in.tex_coords + vec2<f32>(1.0, 1.0) * texel);
+    let luma_m = luminance(center.rgb);
+
+    let luma_min = min(luma_m, min(min(luma_nw, luma_ne), min(luma_sw, luma_se)));
+    let luma_max = max(luma_m, max(max(luma_nw, luma_ne), max(luma_sw, luma_se)));
+
+    // The direction along the edge
+    var dir = vec2<f32>(
+        -((luma_nw + luma_ne) - (luma_sw + luma_se)),
+        (luma_nw + luma_sw) - (luma_ne + luma_se)
+    );
+    let dir_reduce = max((luma_nw + luma_ne + luma_sw + luma_se) * 0.03125, 0.0078125);
+    let inverse_dir_min = 1.0 / (min(abs(dir.x), abs(dir.y)) + dir_reduce);
+    let span = param(0u);
+    dir = clamp(dir * inverse_dir_min, vec2<f32>(-span), vec2<f32>(span)) * texel;
+
+    let color_a = 0.5 * (
+        textureSample(t_input, s_input, in.tex_coords + dir * (1.0 / 3.0 - 0.5)).rgb +
+        textureSample(t_input, s_input, in.tex_coords + dir * (2.0 / 3.0 - 0.5)).rgb
+    );
+    let color_b = color_a * 0.5 + 0.25 * (
+        textureSample(t_input, s_input, in.tex_coords + dir * -0.5).rgb +
+        textureSample(t_input, s_input, in.tex_coords + dir * 0.5).rgb
+    );
+
+    // Skip flat areas, and fall back to the narrower blend if the wide one crossed an edge
+    if (luma_max - luma_min < param(1u)) {
+        return center;
+    }
+
+    let luma_b = luminance(color_b);
+    if (luma_b < luma_min || luma_b > luma_max) {
+        return vec4<f32>(color_a, center.a);
+    }
+    return vec4<f32>(color_b, center.a);
+}
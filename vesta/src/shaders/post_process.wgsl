@@ -0,0 +1,47 @@
+// Shared by every post processing effect. Effects define `fs_main`, which reads the previous
+// effect's output from `t_input` and writes the new color
+
+struct VertexOutput {
+    [[builtin(position)]] position: vec4<f32>;
+    [[location(0)]] tex_coords: vec2<f32>;
+};
+
+struct EffectParams {
+    // Up to 8 parameters, read them with `param`
+    values: array<vec4<f32>, 2>;
+    // The size of a pixel of the input (1 / width, 1 / height), followed by the size in pixels
+    texel_size: vec4<f32>;
+};
+
+[[group(0), binding(0)]]
+var t_input: texture_2d<f32>;
+
+[[group(0), binding(1)]]
+var s_input: sampler;
+
+[[group(0), binding(2)]]
+var<uniform> params: EffectParams;
+
+fn param(index: u32) -> f32 {
+    return params.values[index / 4u][index % 4u];
+}
+
+fn texel_size() -> vec2<f32> {
+    return params.texel_size.xy;
+}
+
+fn luminance(color: vec3<f32>) -> f32 {
+    return dot(color, vec3<f32>(0.2126, 0.7152, 0.0722));
+}
+
+[[stage(vertex)]]
+fn vs_main([[builtin(vertex_index)]] vertex_index: u32) -> VertexOutput {
+    // A single triangle that covers the screen
+    let x = f32(i32(vertex_index) / 2) * 4.0 - 1.0;
+    let y = f32(i32(vertex_index) & 1) * 4.0 - 1.0;
+
+    var out: VertexOutput;
+    out.position = vec4<f32>(x, y, 0.0, 1.0);
+    out.tex_coords = vec2<f32>((x + 1.0) * 0.5, (1.0 - y) * 0.5);
+    return out;
+}
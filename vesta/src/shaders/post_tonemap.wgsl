@@ -0,0 +1,31 @@
+// param(0): exposure, param(1): tonemapper (0 = none, 1 = Reinhard, 2 = ACES)
+
+// Narkowicz's fit of the ACES filmic curve
+fn aces(x: vec3<f32>) -> vec3<f32> {
+    let a = 2.51;
+    let b = 0.03;
+    let c = 2.43;
+    let d = 0.59;
+    let e = 0.14;
+    return clamp((x * (a * x + b)) / (x * (c * x + d) + e), vec3<f32>(0.0), vec3<f32>(1.0));
+}
+
+fn reinhard(x: vec3<f32>) -> vec3<f32> {
+    return x / (vec3<f32>(1.0) + x);
+}
+
+[[stage(fragment)]]
+fn fs_main(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+    let input = textureSample(t_input, s_input, in.tex_coords);
+    let color = input.rgb * param(0u);
+
+    let mode = u32(param(1u));
+    var mapped = clamp(color, vec3<f32>(0.0), vec3<f32>(1.0));
+    if (mode == 1u) {
+        mapped = reinhard(color);
+    } else if (mode == 2u) {
+        mapped = aces(color);
+    }
+
+    return vec4<f32>(mapped, input.a);
+}
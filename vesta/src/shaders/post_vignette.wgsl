@@ -0,0 +1,13 @@
+// param(0): intensity, param(1): radius, param(2): smoothness
+
+[[stage(fragment)]]
+fn fs_main(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+    let input = textureSample(t_input, s_input, in.tex_coords);
+
+    // Keep the vignette round on wide screens
+    let aspect = params.texel_size.z / params.texel_size.w;
+    let offset = (in.tex_coords - vec2<f32>(0.5)) * vec2<f32>(aspect, 1.0);
+
+    let vignette = 1.0 - smoothStep(param(1u) - param(2u), param(1u), length(offset));
+    return vec4<f32>(input.rgb * mix(1.0, vignette, param(0u)), input.a);
+}
@@ -0,0 +1,38 @@
+struct VertexOutput {
+    [[builtin(position)]] position: vec4<f32>;
+    [[location(0)]] direction: vec3<f32>;
+};
+
+struct Skybox {
+    // Inverse of the camera's projection and view (without translation) matrix
+    view_proj_inverse: mat4x4<f32>;
+};
+
+[[group(0), binding(0)]]
+var<uniform> u_skybox: Skybox;
+
+[[group(1), binding(0)]]
+var u_cubemap: texture_cube<f32>;
+
+[[group(1), binding(1)]]
+var u_sampler: sampler;
+
+[[stage(vertex)]]
+fn vs_main([[builtin(vertex_index)]] vertex_index: u32) -> VertexOutput {
+    // A single triangle that covers the screen, on the far plane
+    let x = f32(i32(vertex_index) / 2) * 4.0 - 1.0;
+    let y = f32(i32(vertex_index) & 1) * 4.0 - 1.0;
+
+    let near = u_skybox.view_proj_inverse * vec4<f32>(x, y, 0.0, 1.0);
+    let far = u_skybox.view_proj_inverse * vec4<f32>(x, y, 1.0, 1.0);
+
+    var out: VertexOutput;
+    out.position = vec4<f32>(x, y, 1.0, 1.0);
+    out.direction = far.xyz / far.w - near.xyz / near.w;
+    return out;
+}
+
+[[stage(fragment)]]
+fn fs_main(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+    return textureSample(u_cubemap, u_sampler, normalize(in.direction));
+}
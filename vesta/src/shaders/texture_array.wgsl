@@ -0,0 +1,19 @@
+// Helpers for sampling 2D texture arrays created with `Texture::from_images_array`.
+// Join this source with your shader, for example:
+//
+// [[group(2), binding(0)]]
+// var u_texture_array: texture_2d_array<f32>;
+// [[group(2), binding(1)]]
+// var u_texture_array_sampler: sampler;
+//
+// let color = sample_texture_array(u_texture_array, u_texture_array_sampler, in.tex_coord, in.layer);
+
+// Layers are usually passed from the vertex shader as a float, round so interpolation
+// can never select a neighbouring layer
+fn texture_array_layer(layer: f32) -> i32 {
+    return i32(round(layer));
+}
+
+fn sample_texture_array(t: texture_2d_array<f32>, s: sampler, tex_coord: vec2<f32>, layer: f32) -> vec4<f32> {
+    return textureSample(t, s, tex_coord, texture_array_layer(layer));
+}
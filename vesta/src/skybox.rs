@@ -0,0 +1,98 @@
+use anyhow::*;
+use cgmath::{Matrix4, SquareMatrix, Vector4};
+use crevice::std140::AsStd140;
+
+use crate::{Camera, RenderPipelineBuilder, Renderer, Texture, UniformBuffer, UniformBufferUtils};
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, AsStd140)]
+pub struct SkyboxUniform {
+    pub view_proj_inverse: Matrix4<f32>,
+}
+
+unsafe impl bytemuck::Zeroable for SkyboxUniform {}
+unsafe impl bytemuck::Pod for SkyboxUniform {}
+
+/// Draws a cubemap behind everything else in the scene. Render this in the main render pass,
+/// either before or after opaque geometry
+pub struct Skybox {
+    pub cubemap: Texture,
+    pub uniform_buffer: UniformBuffer<SkyboxUniform>,
+    render_pipeline: wgpu::RenderPipeline,
+}
+
+impl Renderer {
+    /// Create a skybox from a cubemap texture, see `Texture::from_cubemap`
+    pub fn create_skybox(&self, cubemap: Texture) -> Result<Skybox> {
+        Skybox::new(cubemap, self)
+    }
+}
+
+impl Skybox {
+    pub fn new(cubemap: Texture, renderer: &Renderer) -> Result<Self> {
+        if cubemap.bind_group.is_none() {
+            bail!("The skybox cubemap has no bind group");
+        }
+
+        let layout = renderer
+            .device
+            .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Skybox Render Pipeline Layout"),
+                bind_group_layouts: &[
+                    &UniformBufferUtils::create_bind_group_layout(
+                        wgpu::ShaderStages::VERTEX,
+                        &renderer.device,
+                    ),
+                    &Texture::create_cube_bind_group_layout(&renderer.device),
+                ],
+                push_constant_ranges: &[],
+            });
+
+        // The skybox is drawn on the far plane, so anything else drawn will be in front of it
+        let render_pipeline = RenderPipelineBuilder::new(renderer, "Skybox Render Pipeline")
+            .with_shader_source(wgpu::ShaderSource::Wgsl(
+                include_str!("shaders/skybox.wgsl").into(),
+            ))
+            .with_layout(&layout)
+            .with_vertex_buffer_layout(&[])
+            .with_depth_write_enabled(false)
+            .with_depth_compare(wgpu::CompareFunction::LessEqual)
+            .with_cull_mode(None)
+            .build(&renderer.device)?;
+
+        let uniform_buffer = UniformBuffer::new(
+            "Skybox Uniform Buffer",
+            wgpu::ShaderStages::VERTEX,
+            SkyboxUniform {
+                view_proj_inverse: Matrix4::identity(),
+            },
+            renderer,
+        );
+
+        Ok(Self {
+            cubemap,
+            uniform_buffer,
+            render_pipeline,
+        })
+    }
+
+    /// Update the skybox to match the camera's rotation and projection, call this whenever the
+    /// camera uniforms are updated
+    pub fn update(&mut self, camera: &Camera, renderer: &Renderer) {
+        // The skybox is infinitely far away, so only the camera's rotation matters
+        let mut view = camera.calc_matrix();
+        view.w = Vector4::new(0.0, 0.0, 0.0, 1.0);
+
+        if let Some(inverse) = (camera.projection.calc_matrix() * view).invert() {
+            self.uniform_buffer.data.view_proj_inverse = inverse;
+            renderer.write_uniform_buffer(&self.uniform_buffer);
+        }
+    }
+
+    pub fn render<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
+        render_pass.set_pipeline(&self.render_pipeline);
+        render_pass.set_bind_group(0, &self.uniform_buffer.bind_group, &[]);
+        render_pass.set_bind_group(1, self.cubemap.bind_group.as_ref().unwrap(), &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+}
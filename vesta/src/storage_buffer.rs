@@ -0,0 +1,321 @@
+use std::ops::Range;
+
+use anyhow::*;
+use bytemuck::Zeroable;
+use crevice::std430::{AsStd430, Std430};
+
+use crate::{Renderer, UniformBufferUtils};
+
+/// If shaders can write to a storage buffer
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StorageAccess {
+    /// `var<storage, read>`, this is required for buffers used in vertex shaders
+    ReadOnly,
+    /// `var<storage, read_write>`
+    ReadWrite,
+}
+
+/// A holder for a storage buffer, contains the data and raw buffer. Storage buffers hold a
+/// runtime sized array of `T` (`array<T>` in WGSL) using the std430 layout, and can be
+/// written to by shaders
+pub struct StorageBuffer<T>
+where
+    T: AsStd430,
+{
+    pub data: Vec<T>,
+    pub buffer: wgpu::Buffer,
+    pub bind_group: wgpu::BindGroup,
+    name: String,
+    visibility: wgpu::ShaderStages,
+    access: StorageAccess,
+    capacity: usize,
+}
+
+impl Renderer {
+    /// Write all of the data in the storage buffer to the GPU. If there is more data than the
+    /// buffer can hold, the buffer grows and its bind group is recreated (and true is returned)
+    pub fn write_storage_buffer<T: AsStd430>(&self, storage_buffer: &mut StorageBuffer<T>) -> bool {
+        let grown = storage_buffer.reserve(storage_buffer.data.len(), self);
+
+        self.queue.write_buffer(
+            &storage_buffer.buffer,
+            0,
+            &StorageBuffer::encode(&storage_buffer.data),
+        );
+
+        grown
+    }
+
+    /// Write some of the elements in the storage buffer to the GPU, e.g. after changing a few
+    /// entries of a large array. The range must fit within the buffer's capacity
+    pub fn write_storage_buffer_range<T: AsStd430>(
+        &self,
+        storage_buffer: &StorageBuffer<T>,
+        range: Range<usize>,
+    ) -> Result<()> {
+        if range.end > storage_buffer.data.len() || range.end > storage_buffer.capacity {
+            bail!(
+                "Range {:?} is outside of the storage buffer '{}' (length {}, capacity {})",
+                range,
+                storage_buffer.name,
+                storage_buffer.data.len(),
+                storage_buffer.capacity
+            );
+        }
+
+        let offset = range.start * StorageBuffer::<T>::stride();
+        self.queue.write_buffer(
+            &storage_buffer.buffer,
+            offset as wgpu::BufferAddress,
+            &StorageBuffer::encode(&storage_buffer.data[range]),
+        );
+
+        Ok(())
+    }
+
+    /// Read every element a storage buffer can hold back from the GPU, blocking until it has
+    /// finished
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn read_storage_buffer<T: AsStd430>(
+        &self,
+        storage_buffer: &StorageBuffer<T>,
+    ) -> Result<Vec<T>> {
+        storage_buffer.read(&self.device, &self.queue)
+    }
+
+    /// Read every element a storage buffer can hold back from the GPU
+    pub async fn read_storage_buffer_async<T: AsStd430>(
+        &self,
+        storage_buffer: &StorageBuffer<T>,
+    ) -> Result<Vec<T>> {
+        storage_buffer.read_async(&self.device, &self.queue).await
+    }
+}
+
+impl<T: AsStd430> StorageBuffer<T> {
+    /// Create a new storage buffer holding the data. Storage buffers that are bound to vertex
+    /// shaders must be read only
+    pub fn new(
+        name: &str,
+        visibility: wgpu::ShaderStages,
+        access: StorageAccess,
+        data: Vec<T>,
+        renderer: &Renderer,
+    ) -> Self {
+        let capacity = data.len().max(1);
+        let (buffer, bind_group) = Self::create_buffer(
+            name,
+            visibility,
+            access,
+            capacity,
+            &Self::encode(&data),
+            renderer,
+        );
+
+        Self {
+            data,
+            buffer,
+            bind_group,
+            name: name.to_string(),
+            visibility,
+            access,
+            capacity,
+        }
+    }
+
+    /// Create an empty storage buffer with room for `capacity` elements (at least one)
+    pub fn with_capacity(
+        name: &str,
+        visibility: wgpu::ShaderStages,
+        access: StorageAccess,
+        capacity: usize,
+        renderer: &Renderer,
+    ) -> Self {
+        let capacity = capacity.max(1);
+        let (buffer, bind_group) =
+            Self::create_buffer(name, visibility, access, capacity, &[], renderer);
+
+        Self {
+            data: Vec::new(),
+            buffer,
+            bind_group,
+            name: name.to_string(),
+            visibility,
+            access,
+            capacity,
+        }
+    }
+
+    /// Create a buffer with room for `capacity` elements, starting with the contents
+    fn create_buffer(
+        name: &str,
+        visibility: wgpu::ShaderStages,
+        access: StorageAccess,
+        capacity: usize,
+        contents: &[u8],
+        renderer: &Renderer,
+    ) -> (wgpu::Buffer, wgpu::BindGroup) {
+        let buffer = renderer.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some(name),
+            size: (capacity * Self::stride()) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::STORAGE
+                | wgpu::BufferUsages::COPY_DST
+                | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: true,
+        });
+        buffer.slice(..).get_mapped_range_mut()[..contents.len()].copy_from_slice(contents);
+        buffer.unmap();
+
+        let bind_group = renderer
+            .device
+            .create_bind_group(&wgpu::BindGroupDescriptor {
+                layout: &renderer.get_bind_group_layout(
+                    "storage_bind_group_layout",
+                    &[UniformBufferUtils::storage_layout_entry(
+                        visibility,
+                        access == StorageAccess::ReadOnly,
+                    )],
+                ),
+                entries: &[wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: buffer.as_entire_binding(),
+                }],
+                label: Some("storage_bind_group"),
+            });
+
+        (buffer, bind_group)
+    }
+
+    /// Make sure the buffer can hold `capacity` elements, growing it if needed. The contents of
+    /// the GPU buffer are lost when it grows. Returns true if the buffer (and bind group) were
+    /// recreated
+    pub fn reserve(&mut self, capacity: usize, renderer: &Renderer) -> bool {
+        if capacity <= self.capacity {
+            return false;
+        }
+
+        // Grow to at least double, so pushing elements one at a time is not too slow
+        let capacity = capacity.max(self.capacity * 2);
+        let (buffer, bind_group) = Self::create_buffer(
+            &self.name,
+            self.visibility,
+            self.access,
+            capacity,
+            &[],
+            renderer,
+        );
+
+        self.buffer = buffer;
+        self.bind_group = bind_group;
+        self.capacity = capacity;
+
+        true
+    }
+
+    /// The number of elements the GPU buffer can hold
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn access(&self) -> StorageAccess {
+        self.access
+    }
+
+    /// The size in bytes of each element in the buffer (the array stride in shaders)
+    pub fn stride() -> usize {
+        let alignment = <T::Output as Std430>::ALIGNMENT;
+        (std::mem::size_of::<T::Output>() + alignment - 1) & !(alignment - 1)
+    }
+
+    /// Read every element the buffer can hold (`capacity`, not just the length of `data`) back
+    /// from the GPU, as shaders may have written to any of them. On native the device is
+    /// polled until the copy has finished, as nothing else polls it outside a frame. On the
+    /// web the browser resolves the future
+    pub async fn read_async(&self, device: &wgpu::Device, queue: &wgpu::Queue) -> Result<Vec<T>> {
+        let staging_buffer = copy_to_staging_buffer(&self.buffer, self.read_size(), device, queue);
+
+        let buffer_slice = staging_buffer.slice(..);
+        let mapping = buffer_slice.map_async(wgpu::MapMode::Read);
+        #[cfg(not(target_arch = "wasm32"))]
+        device.poll(wgpu::Maintain::Wait);
+        mapping.await?;
+
+        let data = self.decode(&buffer_slice.get_mapped_range());
+        staging_buffer.unmap();
+
+        Ok(data)
+    }
+
+    /// Read every element the buffer can hold back from the GPU (see `read_async`), blocking
+    /// until it has finished
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn read(&self, device: &wgpu::Device, queue: &wgpu::Queue) -> Result<Vec<T>> {
+        let staging_buffer = copy_to_staging_buffer(&self.buffer, self.read_size(), device, queue);
+
+        let buffer_slice = staging_buffer.slice(..);
+        let mapping = buffer_slice.map_async(wgpu::MapMode::Read);
+        device.poll(wgpu::Maintain::Wait);
+        futures::executor::block_on(mapping)?;
+
+        let data = self.decode(&buffer_slice.get_mapped_range());
+        staging_buffer.unmap();
+
+        Ok(data)
+    }
+
+    /// The number of bytes to read back, the whole buffer
+    fn read_size(&self) -> wgpu::BufferAddress {
+        (self.capacity * Self::stride()) as wgpu::BufferAddress
+    }
+
+    fn encode(data: &[T]) -> Vec<u8> {
+        let stride = Self::stride();
+        let mut bytes = vec![0; data.len() * stride];
+
+        for (element, chunk) in data.iter().zip(bytes.chunks_exact_mut(stride)) {
+            let value = element.as_std430();
+            let value_bytes = value.as_bytes();
+            chunk[..value_bytes.len()].copy_from_slice(value_bytes);
+        }
+
+        bytes
+    }
+
+    fn decode(&self, bytes: &[u8]) -> Vec<T> {
+        let size = std::mem::size_of::<T::Output>();
+
+        bytes
+            .chunks_exact(Self::stride())
+            .take(self.capacity)
+            .map(|chunk| {
+                // Copy into a value, as the mapped bytes may not be aligned
+                let mut value = T::Output::zeroed();
+                bytemuck::bytes_of_mut(&mut value).copy_from_slice(&chunk[..size]);
+                T::from_std430(value)
+            })
+            .collect()
+    }
+}
+
+/// Copy the start of a buffer into a new buffer that can be mapped for reading
+fn copy_to_staging_buffer(
+    buffer: &wgpu::Buffer,
+    size: wgpu::BufferAddress,
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+) -> wgpu::Buffer {
+    let staging_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Storage Readback Buffer"),
+        size,
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+        label: Some("Storage Readback Encoder"),
+    });
+    encoder.copy_buffer_to_buffer(buffer, 0, &staging_buffer, 0, size);
+    queue.submit(std::iter::once(encoder.finish()));
+
+    staging_buffer
+}
@@ -5,6 +5,15 @@ use image::GenericImageView;
 
 use crate::renderer::Renderer;
 
+/// How color values in an image are encoded
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ColorSpace {
+    /// Colors (e.g. albedo maps), converted from sRGB to linear when sampled
+    Srgb,
+    /// Data (e.g. normal maps or heightmaps), sampled exactly as stored
+    Linear,
+}
+
 pub struct TextureConfig {
     pub sampler_address_mode_u: wgpu::AddressMode,
     pub sampler_address_mode_v: wgpu::AddressMode,
@@ -12,6 +21,21 @@ pub struct TextureConfig {
     pub sampler_mag_filter: wgpu::FilterMode,
     pub sampler_min_filter: wgpu::FilterMode,
     pub sampler_mipmap_filter: wgpu::FilterMode,
+    /// Generate a full mip chain on the CPU when the texture is created
+    pub generate_mipmaps: bool,
+    /// When generating mipmaps for a texture atlas, set this to the size of each tile. Tiles are
+    /// filtered on their own (clamping to the tile edge), so colors never bleed between tiles.
+    /// The mip chain stops once a tile is a single pixel
+    pub mipmap_atlas_tile_size: Option<(u32, u32)>,
+    /// Pixels of padding around each atlas tile, filled by extending the tile's edge pixels so
+    /// linear filtering never samples the next tile. Each tile is placed in a cell of
+    /// `tile + 2 * padding` pixels, so the texture is larger than the image. The padding halves
+    /// with each mip level, and the mip chain stops once it can't be halved evenly (use a power
+    /// of two to keep more levels)
+    pub mipmap_atlas_padding: u32,
+    /// How the color channels of the image are encoded. Use `Linear` for data such as normal
+    /// maps, otherwise they will be "corrected" when sampled
+    pub color_space: ColorSpace,
 }
 
 impl Default for TextureConfig {
@@ -23,6 +47,10 @@ impl Default for TextureConfig {
             sampler_mag_filter: wgpu::FilterMode::Linear,
             sampler_min_filter: wgpu::FilterMode::Nearest,
             sampler_mipmap_filter: wgpu::FilterMode::Nearest,
+            generate_mipmaps: false,
+            mipmap_atlas_tile_size: None,
+            mipmap_atlas_padding: 0,
+            color_space: ColorSpace::Srgb,
         }
     }
 }
@@ -33,6 +61,8 @@ pub struct Texture {
     pub view: wgpu::TextureView,
     pub sampler: wgpu::Sampler,
     pub bind_group: Option<wgpu::BindGroup>,
+    pub size: wgpu::Extent3d,
+    pub format: wgpu::TextureFormat,
 }
 
 impl Renderer {
@@ -42,7 +72,7 @@ impl Renderer {
         label: Option<&str>,
         config: TextureConfig,
     ) -> Result<Texture> {
-        Texture::from_bytes(&self.device, &self.queue, bytes, label, config)
+        Texture::from_bytes(self, bytes, label, config)
     }
 
     pub fn create_texture_from_image(
@@ -51,88 +81,371 @@ impl Renderer {
         label: Option<&str>,
         config: TextureConfig,
     ) -> Result<Texture> {
-        Texture::from_image(&self.device, &self.queue, image, label, config)
+        Texture::from_image(self, image, label, config)
+    }
+
+    /// Create a 2D texture array from same sized images, one layer per image
+    pub fn create_texture_array(
+        &self,
+        images: &[image::DynamicImage],
+        label: Option<&str>,
+        config: TextureConfig,
+    ) -> Result<Texture> {
+        Texture::from_images_array(self, images, label, config)
+    }
+
+    /// Create a cubemap from six square faces, in the order +X, -X, +Y, -Y, +Z, -Z
+    pub fn create_cubemap(
+        &self,
+        faces: &[image::DynamicImage; 6],
+        label: Option<&str>,
+        config: TextureConfig,
+    ) -> Result<Texture> {
+        Texture::from_cubemap(self, faces, label, config)
+    }
+
+    /// Create a cubemap from an equirectangular image, such as an HDR environment map
+    pub fn create_cubemap_from_equirectangular(
+        &self,
+        image: &image::DynamicImage,
+        face_size: u32,
+        label: Option<&str>,
+        config: TextureConfig,
+    ) -> Result<Texture> {
+        Texture::from_equirectangular(self, image, face_size, label, config)
+    }
+
+    /// Copy a texture back to the CPU, blocking until the GPU has finished
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn read_texture_to_image(&self, texture: &Texture) -> Result<image::RgbaImage> {
+        texture.read_to_image(&self.device, &self.queue)
+    }
+
+    /// Copy a texture back to the CPU
+    pub async fn read_texture_to_image_async(&self, texture: &Texture) -> Result<image::RgbaImage> {
+        texture.read_to_image_async(&self.device, &self.queue).await
     }
 
     /// Create a depth texture. This is a special type of texture that can be used for the
-    /// depth buffer.
+    /// depth buffer. It matches the size and sample count of the main render pass
     pub fn create_depth_texture(&self, label: Option<&str>) -> Result<Texture> {
-        Texture::create_depth(&self.device, &self.surface_config, label)
+        Texture::create_depth_with_size(
+            &self.device,
+            self.surface_config.width,
+            self.surface_config.height,
+            self.sample_count,
+            label,
+        )
     }
 }
 
 impl Texture {
     // The DEPTH texture format used for this application
     pub const DEPTH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
+    // The depth format used when stencil testing is needed
+    pub const DEPTH_STENCIL_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth24PlusStencil8;
 
     /// Create a texture from bytes
     pub fn from_bytes(
-        device: &wgpu::Device,
-        queue: &wgpu::Queue,
+        renderer: &Renderer,
         bytes: &[u8],
         label: Option<&str>,
         config: TextureConfig,
     ) -> Result<Self> {
         let img = image::load_from_memory(bytes)?;
-        Self::from_image(device, queue, &img, label, config)
+        Self::from_image(renderer, &img, label, config)
     }
 
-    /// Create a texture from an image
+    /// Create a texture from an image. The texture format is picked based on the image's
+    /// color type and the configured color space:
+    ///
+    /// - 8-bit RGB(A) uses `Rgba8UnormSrgb` or `Rgba8Unorm`
+    /// - 8-bit grayscale (with alpha) uses `R8Unorm` (`Rg8Unorm`), these are always linear
+    /// - 16-bit grayscale (with alpha) uses `R16Unorm` (`Rg16Unorm`) when the device supports
+    ///   `TEXTURE_FORMAT_16BIT_NORM`, keeping the full precision of heightmaps. Otherwise it
+    ///   uses `R16Float` (`Rg16Float`), which has 11 bits of precision
+    /// - 16-bit and float RGB(A) images use `Rgba16Float`, sRGB colors are converted to linear
+    ///   when uploaded
     fn from_image(
-        device: &wgpu::Device,
-        queue: &wgpu::Queue,
+        renderer: &Renderer,
+        img: &image::DynamicImage,
+        label: Option<&str>,
+        config: TextureConfig,
+    ) -> Result<Self> {
+        Self::from_layers(
+            renderer,
+            std::slice::from_ref(img),
+            wgpu::TextureViewDimension::D2,
+            label,
+            config,
+        )
+    }
+
+    /// Create a 2D texture array, with one layer per image. Every image must be the same size
+    /// and color type. Mipmaps are generated for each layer separately
+    pub fn from_images_array(
+        renderer: &Renderer,
+        images: &[image::DynamicImage],
+        label: Option<&str>,
+        config: TextureConfig,
+    ) -> Result<Self> {
+        Self::from_layers(
+            renderer,
+            images,
+            wgpu::TextureViewDimension::D2Array,
+            label,
+            config,
+        )
+    }
+
+    /// Create a cubemap from six square faces, in the order +X, -X, +Y, -Y, +Z, -Z
+    pub fn from_cubemap(
+        renderer: &Renderer,
+        faces: &[image::DynamicImage; 6],
+        label: Option<&str>,
+        config: TextureConfig,
+    ) -> Result<Self> {
+        if faces[0].width() != faces[0].height() {
+            bail!("Cubemap faces must be square");
+        }
+
+        Self::from_layers(
+            renderer,
+            faces,
+            wgpu::TextureViewDimension::Cube,
+            label,
+            config,
+        )
+    }
+
+    /// Create a cubemap from an equirectangular (latitude / longitude) image, such as an HDR
+    /// environment map. The center of the image faces -Z
+    pub fn from_equirectangular(
+        renderer: &Renderer,
+        img: &image::DynamicImage,
+        face_size: u32,
+        label: Option<&str>,
+        config: TextureConfig,
+    ) -> Result<Self> {
+        let faces = Self::equirectangular_to_cube_faces(img, face_size)?;
+        Self::from_cubemap(renderer, &faces, label, config)
+    }
+
+    /// Project an equirectangular image onto the six faces of a cube (bilinear filtered). The
+    /// faces keep the bit depth of the image
+    pub fn equirectangular_to_cube_faces(
         img: &image::DynamicImage,
+        face_size: u32,
+    ) -> Result<[image::DynamicImage; 6]> {
+        use image::DynamicImage::*;
+
+        if face_size == 0 {
+            bail!("Cubemap faces must be at least one pixel");
+        }
+
+        let source = img.to_rgba32f();
+        let (width, height) = source.dimensions();
+
+        let sample = |x: f32, y: f32| -> [f32; 4] {
+            // Wrap horizontally around the sphere, clamp at the poles
+            let x = x - 0.5;
+            let y = (y - 0.5).clamp(0.0, height as f32 - 1.0);
+            let (x0, y0) = (x.floor(), y.floor());
+            let (fx, fy) = (x - x0, y - y0);
+
+            let pixel = |px: f32, py: f32| {
+                let px = (px as i64).rem_euclid(width as i64) as u32;
+                let py = (py as u32).min(height - 1);
+                source.get_pixel(px, py).0
+            };
+
+            let (a, b) = (pixel(x0, y0), pixel(x0 + 1.0, y0));
+            let (c, d) = (pixel(x0, y0 + 1.0), pixel(x0 + 1.0, y0 + 1.0));
+
+            let mut result = [0.0; 4];
+            for i in 0..4 {
+                let top = a[i] + (b[i] - a[i]) * fx;
+                let bottom = c[i] + (d[i] - c[i]) * fx;
+                result[i] = top + (bottom - top) * fy;
+            }
+            result
+        };
+
+        let project_face = |face: usize| {
+            let projected = image::Rgba32FImage::from_fn(face_size, face_size, |x, y| {
+                // Position on the face, from -1 to 1
+                let u = 2.0 * (x as f32 + 0.5) / face_size as f32 - 1.0;
+                let v = 2.0 * (y as f32 + 0.5) / face_size as f32 - 1.0;
+
+                let direction = match face {
+                    0 => cgmath::Vector3::new(1.0, -v, -u),
+                    1 => cgmath::Vector3::new(-1.0, -v, u),
+                    2 => cgmath::Vector3::new(u, 1.0, v),
+                    3 => cgmath::Vector3::new(u, -1.0, -v),
+                    4 => cgmath::Vector3::new(u, -v, 1.0),
+                    _ => cgmath::Vector3::new(-u, -v, -1.0),
+                };
+                let direction = cgmath::InnerSpace::normalize(direction);
+
+                let longitude = direction.x.atan2(-direction.z);
+                let latitude = direction.y.clamp(-1.0, 1.0).acos();
+
+                image::Rgba(sample(
+                    (0.5 + longitude / std::f32::consts::TAU) * width as f32,
+                    latitude / std::f32::consts::PI * height as f32,
+                ))
+            });
+
+            // Convert back so the faces get the same texture format as the source image
+            let projected = ImageRgba32F(projected);
+            match img {
+                ImageRgb32F(_) | ImageRgba32F(_) => projected,
+                ImageLuma16(_) | ImageLumaA16(_) | ImageRgb16(_) | ImageRgba16(_) => {
+                    ImageRgba16(projected.into_rgba16())
+                }
+                _ => ImageRgba8(projected.into_rgba8()),
+            }
+        };
+
+        Ok([0, 1, 2, 3, 4, 5].map(project_face))
+    }
+
+    /// Create a texture with one layer per image, viewed with the specified dimension
+    fn from_layers(
+        renderer: &Renderer,
+        images: &[image::DynamicImage],
+        view_dimension: wgpu::TextureViewDimension,
         label: Option<&str>,
         config: TextureConfig,
     ) -> Result<Self> {
-        let rgba = img.as_rgba8().unwrap();
-        let dimensions = img.dimensions();
+        let (device, queue) = (&renderer.device, &renderer.queue);
+        let first = match images.first() {
+            Some(first) => first,
+            None => bail!("A texture requires at least one image"),
+        };
 
+        let features = device.features();
+        let format = Self::format_for_image(first, config.color_space, features)?;
+        let dimensions = first.dimensions();
+
+        for (layer, img) in images.iter().enumerate() {
+            if img.dimensions() != dimensions {
+                bail!(
+                    "Texture layer {} is {}x{}, expected {}x{}",
+                    layer,
+                    img.width(),
+                    img.height(),
+                    dimensions.0,
+                    dimensions.1
+                );
+            }
+
+            if Self::format_for_image(img, config.color_space, features)? != format {
+                bail!("Texture layer {} has a different color type", layer);
+            }
+        }
+
+        // For every layer, either just the image or the image followed by each mip level
+        let layers: Vec<Vec<TextureLevel>> = images
+            .iter()
+            .map(|img| {
+                let base_level = Self::decode_image(img, config.color_space);
+                let chain = if config.generate_mipmaps {
+                    Self::generate_mip_chain(
+                        base_level,
+                        config.mipmap_atlas_tile_size,
+                        config.mipmap_atlas_padding,
+                    )
+                } else {
+                    vec![base_level]
+                };
+
+                match config.mipmap_atlas_tile_size {
+                    Some(tile_size) if config.mipmap_atlas_padding > 0 => {
+                        Self::pad_atlas_tiles(chain, tile_size, config.mipmap_atlas_padding)
+                    }
+                    _ => chain,
+                }
+            })
+            .collect();
+
+        // Padding atlas tiles makes the texture larger than the images
         let size = wgpu::Extent3d {
-            width: dimensions.0,
-            height: dimensions.1,
-            depth_or_array_layers: 1,
+            width: layers[0][0].width,
+            height: layers[0][0].height,
+            depth_or_array_layers: images.len() as u32,
         };
         let texture = device.create_texture(&wgpu::TextureDescriptor {
             label,
             size,
-            mip_level_count: 1,
+            mip_level_count: layers[0].len() as u32,
             sample_count: 1,
             dimension: wgpu::TextureDimension::D2,
-            format: wgpu::TextureFormat::Rgba8UnormSrgb,
-            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            format,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING
+                | wgpu::TextureUsages::COPY_DST
+                | wgpu::TextureUsages::COPY_SRC,
         });
 
-        queue.write_texture(
-            wgpu::ImageCopyTexture {
-                texture: &texture,
-                mip_level: 0,
-                origin: wgpu::Origin3d::ZERO,
-                aspect: wgpu::TextureAspect::All,
-            },
-            rgba,
-            wgpu::ImageDataLayout {
-                offset: 0,
-                bytes_per_row: NonZeroU32::new(4 * dimensions.0),
-                rows_per_image: NonZeroU32::new(dimensions.1),
-            },
-            size,
-        );
+        let block_size = format.describe().block_size as u32;
+        for (layer, mip_chain) in layers.iter().enumerate() {
+            for (mip_level, level) in mip_chain.iter().enumerate() {
+                queue.write_texture(
+                    wgpu::ImageCopyTexture {
+                        texture: &texture,
+                        mip_level: mip_level as u32,
+                        origin: wgpu::Origin3d {
+                            x: 0,
+                            y: 0,
+                            z: layer as u32,
+                        },
+                        aspect: wgpu::TextureAspect::All,
+                    },
+                    &level.encode(format),
+                    wgpu::ImageDataLayout {
+                        offset: 0,
+                        bytes_per_row: NonZeroU32::new(block_size * level.width),
+                        rows_per_image: NonZeroU32::new(level.height),
+                    },
+                    wgpu::Extent3d {
+                        width: level.width,
+                        height: level.height,
+                        depth_or_array_layers: 1,
+                    },
+                );
+            }
+        }
 
-        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        // Formats that can't be filtered (e.g. `R32Float`) must use a nearest sampler
+        let filterable = format.describe().guaranteed_format_features.filterable;
+        let filter = |mode| {
+            if filterable {
+                mode
+            } else {
+                wgpu::FilterMode::Nearest
+            }
+        };
+
+        let view = texture.create_view(&wgpu::TextureViewDescriptor {
+            dimension: Some(view_dimension),
+            ..Default::default()
+        });
         let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
             address_mode_u: config.sampler_address_mode_u,
             address_mode_v: config.sampler_address_mode_v,
             address_mode_w: config.sampler_address_mode_w,
-            mag_filter: config.sampler_mag_filter,
-            min_filter: config.sampler_min_filter,
-            mipmap_filter: config.sampler_mipmap_filter,
+            mag_filter: filter(config.sampler_mag_filter),
+            min_filter: filter(config.sampler_min_filter),
+            mipmap_filter: filter(config.sampler_mipmap_filter),
             ..Default::default()
         });
 
         // Create the appropriate bind group for the input data
         let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            layout: &Self::create_bind_group_layout(device),
+            layout: &renderer.get_bind_group_layout(
+                "texture_bind_group_layout",
+                &Self::layout_entries(view_dimension, filterable),
+            ),
             entries: &[
                 wgpu::BindGroupEntry {
                     binding: 0,
@@ -151,6 +464,8 @@ impl Texture {
             view,
             sampler,
             bind_group: Some(bind_group),
+            size,
+            format,
         })
     }
 
@@ -162,9 +477,28 @@ impl Texture {
         label: Option<&str>,
     ) -> Result<Self> {
         // Size of depth texture should match the swap chain descriptor
+        Self::create_depth_with_size(
+            device,
+            surface_config.width,
+            surface_config.height,
+            1,
+            label,
+        )
+    }
+
+    /// Create a depth texture of a specific size and sample count. Single sampled depth
+    /// textures also get a bind group (see `create_depth_bind_group_layout`), so they can be
+    /// sampled by shaders (e.g. for shadow maps)
+    pub fn create_depth_with_size(
+        device: &wgpu::Device,
+        width: u32,
+        height: u32,
+        sample_count: u32,
+        label: Option<&str>,
+    ) -> Result<Self> {
         let size = wgpu::Extent3d {
-            width: surface_config.width,
-            height: surface_config.height,
+            width: width.max(1),
+            height: height.max(1),
             depth_or_array_layers: 1,
         };
 
@@ -173,10 +507,12 @@ impl Texture {
             label,
             size,
             mip_level_count: 1,
-            sample_count: 1,
+            sample_count,
             dimension: wgpu::TextureDimension::D2, // 2D texture
             format: Self::DEPTH_FORMAT,
-            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                | wgpu::TextureUsages::TEXTURE_BINDING
+                | wgpu::TextureUsages::COPY_SRC,
         };
 
         // Create the texture based on the descriptor
@@ -197,15 +533,434 @@ impl Texture {
             ..Default::default()
         });
 
+        // Multisampled textures cannot be sampled with a regular sampler
+        let bind_group = (sample_count == 1).then(|| {
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                layout: &Self::create_depth_bind_group_layout(device),
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(&view),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::Sampler(&sampler),
+                    },
+                ],
+                label: Some("depth_texture_bind_group"),
+            })
+        });
+
+        Ok(Self {
+            texture,
+            view,
+            sampler,
+            bind_group,
+            size,
+            format: Self::DEPTH_FORMAT,
+        })
+    }
+
+    /// Create a depth texture with a stencil aspect, for use with pipelines that have stencil
+    /// testing enabled. These cannot be sampled or read back
+    pub fn create_depth_stencil_with_size(
+        device: &wgpu::Device,
+        width: u32,
+        height: u32,
+        sample_count: u32,
+        label: Option<&str>,
+    ) -> Result<Self> {
+        let size = wgpu::Extent3d {
+            width: width.max(1),
+            height: height.max(1),
+            depth_or_array_layers: 1,
+        };
+
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label,
+            size,
+            mip_level_count: 1,
+            sample_count,
+            dimension: wgpu::TextureDimension::D2,
+            format: Self::DEPTH_STENCIL_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor::default());
+
         Ok(Self {
             texture,
             view,
             sampler,
             bind_group: None,
+            size,
+            format: Self::DEPTH_STENCIL_FORMAT,
+        })
+    }
+
+    /// Copy the first mip level (and layer) of this texture back to the CPU. On native the
+    /// device is polled until the copy has finished, as nothing else polls it outside a frame.
+    /// On the web the browser resolves the future. See `read_to_image` for how formats are
+    /// converted
+    pub async fn read_to_image_async(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> Result<image::RgbaImage> {
+        let (buffer, padded_bytes_per_row) = self.copy_to_staging_buffer(device, queue)?;
+
+        let buffer_slice = buffer.slice(..);
+        let mapping = buffer_slice.map_async(wgpu::MapMode::Read);
+        #[cfg(not(target_arch = "wasm32"))]
+        device.poll(wgpu::Maintain::Wait);
+        mapping.await?;
+
+        let image =
+            self.staging_data_to_image(&buffer_slice.get_mapped_range(), padded_bytes_per_row);
+        buffer.unmap();
+
+        image
+    }
+
+    /// Copy the first mip level (and layer) of this texture back to the CPU, blocking until
+    /// the GPU has finished. Formats are converted as follows:
+    ///
+    /// - 8-bit RGBA and BGRA formats are copied as is
+    /// - `R8Unorm` becomes grayscale, `Rg8Unorm` fills the red and green channels
+    /// - RGBA float formats are treated as linear color, clamped and encoded as sRGB
+    /// - Single and two channel float formats (and depth) are clamped and copied as is
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn read_to_image(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> Result<image::RgbaImage> {
+        let (buffer, padded_bytes_per_row) = self.copy_to_staging_buffer(device, queue)?;
+
+        let buffer_slice = buffer.slice(..);
+        let mapping = buffer_slice.map_async(wgpu::MapMode::Read);
+        device.poll(wgpu::Maintain::Wait);
+        futures::executor::block_on(mapping)?;
+
+        let image =
+            self.staging_data_to_image(&buffer_slice.get_mapped_range(), padded_bytes_per_row);
+        buffer.unmap();
+
+        image
+    }
+
+    /// Copy the first mip level into a buffer that can be mapped. Rows in the buffer are padded
+    /// to `COPY_BYTES_PER_ROW_ALIGNMENT`, the padded row size is returned with the buffer
+    fn copy_to_staging_buffer(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> Result<(wgpu::Buffer, u32)> {
+        let block_size = self.format.describe().block_size as u32;
+        let unpadded_bytes_per_row = block_size * self.size.width;
+        let alignment = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+        let padded_bytes_per_row = (unpadded_bytes_per_row + alignment - 1) & !(alignment - 1);
+
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Texture Readback Buffer"),
+            size: (padded_bytes_per_row * self.size.height) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        let aspect = if self.format.describe().sample_type == wgpu::TextureSampleType::Depth {
+            wgpu::TextureAspect::DepthOnly
+        } else {
+            wgpu::TextureAspect::All
+        };
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Texture Readback Encoder"),
+        });
+        encoder.copy_texture_to_buffer(
+            wgpu::ImageCopyTexture {
+                texture: &self.texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect,
+            },
+            wgpu::ImageCopyBuffer {
+                buffer: &buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: NonZeroU32::new(padded_bytes_per_row),
+                    rows_per_image: NonZeroU32::new(self.size.height),
+                },
+            },
+            wgpu::Extent3d {
+                width: self.size.width,
+                height: self.size.height,
+                depth_or_array_layers: 1,
+            },
+        );
+        queue.submit(std::iter::once(encoder.finish()));
+
+        Ok((buffer, padded_bytes_per_row))
+    }
+
+    /// Convert the padded rows copied by `copy_to_staging_buffer` into an image
+    fn staging_data_to_image(
+        &self,
+        data: &[u8],
+        padded_bytes_per_row: u32,
+    ) -> Result<image::RgbaImage> {
+        use wgpu::TextureFormat::*;
+
+        let unorm = |t: &[u8], i: usize| t[i] as f32 / 255.0;
+        let unorm16 =
+            |t: &[u8], i: usize| u16::from_le_bytes([t[i * 2], t[i * 2 + 1]]) as f32 / 65535.0;
+        let half = |t: &[u8], i: usize| f16_to_f32(u16::from_le_bytes([t[i * 2], t[i * 2 + 1]]));
+        let float = |t: &[u8], i: usize| {
+            f32::from_le_bytes([t[i * 4], t[i * 4 + 1], t[i * 4 + 2], t[i * 4 + 3]])
+        };
+
+        // Float color is linear, so it is encoded like an 8-bit sRGB image would be
+        let encode_srgb = matches!(self.format, Rgba16Float | Rgba32Float);
+        let to_unorm = |v: f32| (v.clamp(0.0, 1.0) * 255.0).round() as u8;
+
+        let block_size = self.format.describe().block_size as usize;
+        let mut image = image::RgbaImage::new(self.size.width, self.size.height);
+
+        for (y, row) in data
+            .chunks_exact(padded_bytes_per_row as usize)
+            .take(self.size.height as usize)
+            .enumerate()
+        {
+            for (x, t) in row
+                .chunks_exact(block_size)
+                .take(self.size.width as usize)
+                .enumerate()
+            {
+                let [r, g, b, a] = match self.format {
+                    Rgba8Unorm | Rgba8UnormSrgb => [0, 1, 2, 3].map(|i| unorm(t, i)),
+                    Bgra8Unorm | Bgra8UnormSrgb => [2, 1, 0, 3].map(|i| unorm(t, i)),
+                    R8Unorm => [unorm(t, 0), unorm(t, 0), unorm(t, 0), 1.0],
+                    Rg8Unorm => [unorm(t, 0), unorm(t, 1), 0.0, 1.0],
+                    R16Unorm => [unorm16(t, 0), unorm16(t, 0), unorm16(t, 0), 1.0],
+                    Rg16Unorm => [unorm16(t, 0), unorm16(t, 1), 0.0, 1.0],
+                    R16Float => [half(t, 0), half(t, 0), half(t, 0), 1.0],
+                    Rg16Float => [half(t, 0), half(t, 1), 0.0, 1.0],
+                    Rgba16Float => [0, 1, 2, 3].map(|i| half(t, i)),
+                    R32Float | Depth32Float => [float(t, 0), float(t, 0), float(t, 0), 1.0],
+                    Rg32Float => [float(t, 0), float(t, 1), 0.0, 1.0],
+                    Rgba32Float => [0, 1, 2, 3].map(|i| float(t, i)),
+                    format => bail!(
+                        "Reading {:?} textures back to the CPU is not supported",
+                        format
+                    ),
+                };
+
+                let pixel = if encode_srgb {
+                    [linear_to_srgb(r), linear_to_srgb(g), linear_to_srgb(b), a]
+                } else {
+                    [r, g, b, a]
+                };
+
+                image.put_pixel(x as u32, y as u32, image::Rgba(pixel.map(to_unorm)));
+            }
+        }
+
+        Ok(image)
+    }
+
+    /// The texture format an image will be uploaded as, see `from_image`
+    pub fn format_for_image(
+        img: &image::DynamicImage,
+        color_space: ColorSpace,
+        features: wgpu::Features,
+    ) -> Result<wgpu::TextureFormat> {
+        use image::DynamicImage::*;
+
+        let norm16 = features.contains(wgpu::Features::TEXTURE_FORMAT_16BIT_NORM);
+
+        Ok(match img {
+            ImageLuma8(_) => wgpu::TextureFormat::R8Unorm,
+            ImageLumaA8(_) => wgpu::TextureFormat::Rg8Unorm,
+            ImageRgb8(_) | ImageRgba8(_) => match color_space {
+                ColorSpace::Srgb => wgpu::TextureFormat::Rgba8UnormSrgb,
+                ColorSpace::Linear => wgpu::TextureFormat::Rgba8Unorm,
+            },
+            ImageLuma16(_) if norm16 => wgpu::TextureFormat::R16Unorm,
+            ImageLumaA16(_) if norm16 => wgpu::TextureFormat::Rg16Unorm,
+            ImageLuma16(_) => wgpu::TextureFormat::R16Float,
+            ImageLumaA16(_) => wgpu::TextureFormat::Rg16Float,
+            ImageRgb16(_) | ImageRgba16(_) | ImageRgb32F(_) | ImageRgba32F(_) => {
+                wgpu::TextureFormat::Rgba16Float
+            }
+            other => bail!("Unsupported image color type {:?}", other.color()),
         })
     }
 
+    /// Convert an image into linear float pixels with the same channel count as the format
+    /// returned by `format_for_image`
+    fn decode_image(img: &image::DynamicImage, color_space: ColorSpace) -> TextureLevel {
+        use image::DynamicImage::*;
+
+        let (width, height) = img.dimensions();
+        let (channels, mut pixels) = match img {
+            ImageLuma8(_) | ImageLuma16(_) => (1, img.to_luma32f().into_raw()),
+            ImageLumaA8(_) | ImageLumaA16(_) => (2, img.to_luma_alpha32f().into_raw()),
+            _ => (4, img.to_rgba32f().into_raw()),
+        };
+
+        // Float images are already linear, everything else is stored as encoded by the
+        // color space. Alpha is always linear
+        let is_float = matches!(img, ImageRgb32F(_) | ImageRgba32F(_));
+        if channels == 4 && color_space == ColorSpace::Srgb && !is_float {
+            for pixel in pixels.chunks_exact_mut(4) {
+                for value in &mut pixel[..3] {
+                    *value = srgb_to_linear(*value);
+                }
+            }
+        }
+
+        TextureLevel {
+            width,
+            height,
+            channels,
+            pixels,
+        }
+    }
+
+    /// Generate every mip level for the image using a box filter, the first level is the
+    /// image itself. Filtering happens on linear values so sRGB images don't darken
+    fn generate_mip_chain(
+        image: TextureLevel,
+        atlas_tile_size: Option<(u32, u32)>,
+        atlas_padding: u32,
+    ) -> Vec<TextureLevel> {
+        let (width, height) = (image.width, image.height);
+        let (tile_width, tile_height) = atlas_tile_size.unwrap_or((width, height));
+        let channels = image.channels;
+
+        // Stop once either the image or an atlas tile is a single pixel
+        let level_count = 32 - width.max(height).max(1).leading_zeros();
+        let level_count = match atlas_tile_size {
+            Some(_) => level_count.min(32 - tile_width.min(tile_height).max(1).leading_zeros()),
+            None => level_count,
+        };
+
+        // Padded tiles need the padding to halve evenly too, see `pad_atlas_tiles`
+        let level_count = match (atlas_tile_size, atlas_padding) {
+            (Some(_), padding) if padding > 0 => level_count.min(padding.trailing_zeros() + 1),
+            _ => level_count,
+        };
+
+        let mut chain = vec![image];
+
+        for level in 1..level_count {
+            let source = chain.last().unwrap();
+
+            // Size of an atlas tile in the source level (the whole image when not an atlas)
+            let source_tile_width = (tile_width >> (level - 1)).max(1);
+            let source_tile_height = (tile_height >> (level - 1)).max(1);
+
+            let mut destination = TextureLevel {
+                width: (width >> level).max(1),
+                height: (height >> level).max(1),
+                channels,
+                pixels: Vec::new(),
+            };
+            destination.pixels =
+                Vec::with_capacity((destination.width * destination.height) as usize * channels);
+
+            for y in 0..destination.height {
+                for x in 0..destination.width {
+                    // Clamp samples to the edge of the tile (or image) this pixel is part of
+                    let tile_x = (x * 2 / source_tile_width) * source_tile_width;
+                    let tile_y = (y * 2 / source_tile_height) * source_tile_height;
+                    let max_x = (tile_x + source_tile_width - 1).min(source.width - 1);
+                    let max_y = (tile_y + source_tile_height - 1).min(source.height - 1);
+
+                    let samples = [
+                        source.pixel((x * 2).min(max_x), (y * 2).min(max_y)),
+                        source.pixel((x * 2 + 1).min(max_x), (y * 2).min(max_y)),
+                        source.pixel((x * 2).min(max_x), (y * 2 + 1).min(max_y)),
+                        source.pixel((x * 2 + 1).min(max_x), (y * 2 + 1).min(max_y)),
+                    ];
+
+                    for channel in 0..channels {
+                        let sum: f32 = samples.iter().map(|sample| sample[channel]).sum();
+                        destination.pixels.push(sum / 4.0);
+                    }
+                }
+            }
+
+            chain.push(destination);
+        }
+
+        chain
+    }
+
+    /// Place each atlas tile of every level in a cell with a gutter around it, filled by
+    /// extending the tile's edge pixels. The tile size and padding halve with each level
+    fn pad_atlas_tiles(
+        chain: Vec<TextureLevel>,
+        (tile_width, tile_height): (u32, u32),
+        padding: u32,
+    ) -> Vec<TextureLevel> {
+        chain
+            .into_iter()
+            .enumerate()
+            .map(|(level, source)| {
+                let tile_width = (tile_width >> level).max(1);
+                let tile_height = (tile_height >> level).max(1);
+                let gutter = padding >> level;
+
+                let columns = tile_count(source.width, tile_width);
+                let rows = tile_count(source.height, tile_height);
+                let (cell_width, cell_height) = (tile_width + gutter * 2, tile_height + gutter * 2);
+
+                let mut destination = TextureLevel {
+                    width: columns * cell_width,
+                    height: rows * cell_height,
+                    channels: source.channels,
+                    pixels: Vec::new(),
+                };
+                destination.pixels = Vec::with_capacity(
+                    (destination.width * destination.height) as usize * source.channels,
+                );
+
+                for y in 0..destination.height {
+                    for x in 0..destination.width {
+                        // Clamp to the edge of the tile this cell holds
+                        let (column, row) = (x / cell_width, y / cell_height);
+                        let tile_x = (x % cell_width).saturating_sub(gutter).min(tile_width - 1);
+                        let tile_y = (y % cell_height)
+                            .saturating_sub(gutter)
+                            .min(tile_height - 1);
+                        let source_x = (column * tile_width + tile_x).min(source.width - 1);
+                        let source_y = (row * tile_height + tile_y).min(source.height - 1);
+
+                        destination
+                            .pixels
+                            .extend_from_slice(source.pixel(source_x, source_y));
+                    }
+                }
+
+                destination
+            })
+            .collect()
+    }
+
+    /// Layout for the bind group of a 2D texture
     pub fn create_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+        Self::create_bind_group_layout_with_dimension(device, wgpu::TextureViewDimension::D2)
+    }
+
+    /// Layout for the bind group of a 2D texture array, see `shaders::TEXTURE_ARRAY`
+    pub fn create_array_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+        Self::create_bind_group_layout_with_dimension(device, wgpu::TextureViewDimension::D2Array)
+    }
+
+    /// Layout for the bind group of a depth texture (`texture_depth_2d`) and its comparison
+    /// sampler (`sampler_comparison`)
+    pub fn create_depth_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
         device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
             entries: &[
                 wgpu::BindGroupLayoutEntry {
@@ -214,18 +969,315 @@ impl Texture {
                     ty: wgpu::BindingType::Texture {
                         multisampled: false,
                         view_dimension: wgpu::TextureViewDimension::D2,
-                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        sample_type: wgpu::TextureSampleType::Depth,
                     },
                     count: None,
                 },
                 wgpu::BindGroupLayoutEntry {
                     binding: 1,
                     visibility: wgpu::ShaderStages::FRAGMENT,
-                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Comparison),
                     count: None,
                 },
             ],
+            label: Some("depth_texture_bind_group_layout"),
+        })
+    }
+
+    /// Layout for the bind group of a cubemap, see `shaders::ENVIRONMENT`
+    pub fn create_cube_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+        Self::create_bind_group_layout_with_dimension(device, wgpu::TextureViewDimension::Cube)
+    }
+
+    /// Layout for the bind group of a texture (binding 0) and its sampler (binding 1)
+    pub fn create_bind_group_layout_with_dimension(
+        device: &wgpu::Device,
+        view_dimension: wgpu::TextureViewDimension,
+    ) -> wgpu::BindGroupLayout {
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &Self::layout_entries(view_dimension, true),
             label: Some("texture_bind_group_layout"),
         })
     }
+
+    /// The layout entries of a texture (binding 0) and its sampler (binding 1), unfilterable
+    /// formats need a non-filtering sampler
+    pub(crate) fn layout_entries(
+        view_dimension: wgpu::TextureViewDimension,
+        filterable: bool,
+    ) -> [wgpu::BindGroupLayoutEntry; 2] {
+        let sampler_type = if filterable {
+            wgpu::SamplerBindingType::Filtering
+        } else {
+            wgpu::SamplerBindingType::NonFiltering
+        };
+
+        [
+            wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    multisampled: false,
+                    view_dimension,
+                    sample_type: wgpu::TextureSampleType::Float { filterable },
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 1,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Sampler(sampler_type),
+                count: None,
+            },
+        ]
+    }
+}
+
+/// The number of tiles needed to cover the size, including a partial tile at the end
+fn tile_count(size: u32, tile_size: u32) -> u32 {
+    match size % tile_size {
+        0 => size / tile_size,
+        _ => size / tile_size + 1,
+    }
+}
+
+/// A single (mip) level of an image, as linear float pixels
+struct TextureLevel {
+    width: u32,
+    height: u32,
+    channels: usize,
+    pixels: Vec<f32>,
+}
+
+impl TextureLevel {
+    fn pixel(&self, x: u32, y: u32) -> &[f32] {
+        let start = (y * self.width + x) as usize * self.channels;
+        &self.pixels[start..start + self.channels]
+    }
+
+    /// Encode the pixels into the bytes expected by the texture format
+    fn encode(&self, format: wgpu::TextureFormat) -> Vec<u8> {
+        let to_unorm = |v: f32| (v.clamp(0.0, 1.0) * 255.0).round() as u8;
+
+        match format {
+            wgpu::TextureFormat::Rgba8UnormSrgb => self
+                .pixels
+                .chunks_exact(4)
+                .flat_map(|p| {
+                    [
+                        to_unorm(linear_to_srgb(p[0])),
+                        to_unorm(linear_to_srgb(p[1])),
+                        to_unorm(linear_to_srgb(p[2])),
+                        to_unorm(p[3]),
+                    ]
+                })
+                .collect(),
+            wgpu::TextureFormat::R16Float
+            | wgpu::TextureFormat::Rg16Float
+            | wgpu::TextureFormat::Rgba16Float => self
+                .pixels
+                .iter()
+                .flat_map(|v| f32_to_f16(*v).to_le_bytes())
+                .collect(),
+            wgpu::TextureFormat::R16Unorm | wgpu::TextureFormat::Rg16Unorm => self
+                .pixels
+                .iter()
+                .flat_map(|v| ((v.clamp(0.0, 1.0) * 65535.0).round() as u16).to_le_bytes())
+                .collect(),
+            _ => self.pixels.iter().map(|v| to_unorm(*v)).collect(),
+        }
+    }
+}
+
+fn srgb_to_linear(v: f32) -> f32 {
+    if v <= 0.04045 {
+        v / 12.92
+    } else {
+        ((v + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(v: f32) -> f32 {
+    if v <= 0.0031308 {
+        v * 12.92
+    } else {
+        1.055 * v.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+/// Convert the bits of a half precision float to a float
+fn f16_to_f32(half: u16) -> f32 {
+    let sign = if half & 0x8000 != 0 { -1.0 } else { 1.0 };
+    let exponent = ((half >> 10) & 0x1f) as i32;
+    let mantissa = (half & 0x3ff) as f32;
+
+    match exponent {
+        // Subnormal (or zero)
+        0 => sign * mantissa * 2f32.powi(-24),
+        // Infinity and NaN
+        0x1f if mantissa == 0.0 => sign * f32::INFINITY,
+        0x1f => f32::NAN,
+        _ => sign * (1.0 + mantissa / 1024.0) * 2f32.powi(exponent - 15),
+    }
+}
+
+/// Convert a float to the bits of a half precision float, rounding to nearest (ties to even)
+fn f32_to_f16(value: f32) -> u16 {
+    let bits = value.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exponent = ((bits >> 23) & 0xff) as i32;
+    let mantissa = bits & 0x7f_ffff;
+
+    // Infinity and NaN
+    if exponent == 0xff {
+        return sign | 0x7c00 | if mantissa != 0 { 0x200 } else { 0 };
+    }
+
+    let exponent = exponent - 127 + 15;
+    if exponent >= 0x1f {
+        // Too large, becomes infinity
+        sign | 0x7c00
+    } else if exponent <= 0 {
+        // Too small for a normal half float, becomes subnormal (or zero)
+        if exponent < -10 {
+            return sign;
+        }
+        let mantissa = mantissa | 0x80_0000;
+        sign | round_shift(mantissa, (14 - exponent) as u32) as u16
+    } else {
+        // A carry from rounding correctly moves into the exponent (and up to infinity)
+        sign | round_shift(((exponent as u32) << 23) | mantissa, 13) as u16
+    }
+}
+
+/// Shift the value right, rounding to nearest with ties to even
+fn round_shift(value: u32, shift: u32) -> u32 {
+    let truncated = value >> shift;
+    let remainder = value & ((1 << shift) - 1);
+    let halfway = 1 << (shift - 1);
+
+    if remainder > halfway || (remainder == halfway && truncated & 1 == 1) {
+        truncated + 1
+    } else {
+        truncated
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{f16_to_f32, f32_to_f16, Texture, TextureLevel};
+
+    /// A 4x4 single channel atlas of 2x2 tiles, each pixel is `10 * tile + pixel`
+    fn atlas() -> TextureLevel {
+        let mut pixels = Vec::new();
+        for y in 0..4 {
+            for x in 0..4 {
+                let tile = (y / 2) * 2 + x / 2;
+                let pixel = (y % 2) * 2 + x % 2;
+                pixels.push((10 * tile + pixel) as f32);
+            }
+        }
+
+        TextureLevel {
+            width: 4,
+            height: 4,
+            channels: 1,
+            pixels,
+        }
+    }
+
+    #[test]
+    fn pads_atlas_tiles_with_their_edges() {
+        let padded = Texture::pad_atlas_tiles(vec![atlas()], (2, 2), 1).remove(0);
+        assert_eq!((padded.width, padded.height), (8, 8));
+
+        // Each 4x4 cell holds a tile with a 1 pixel gutter copied from its nearest edge pixel
+        let expected_cell = |tile: u32| -> Vec<f32> {
+            [0, 0, 1, 1]
+                .iter()
+                .flat_map(|y| {
+                    [0, 0, 1, 1]
+                        .iter()
+                        .map(move |x| (10 * tile + y * 2 + x) as f32)
+                })
+                .collect()
+        };
+
+        for tile in 0..4 {
+            let (cell_x, cell_y) = ((tile % 2) * 4, (tile / 2) * 4);
+            let cell: Vec<f32> = (0..4)
+                .flat_map(|y| (0..4).map(move |x| (x, y)))
+                .map(|(x, y)| padded.pixel(cell_x + x, cell_y + y)[0])
+                .collect();
+
+            assert_eq!(cell, expected_cell(tile), "tile {}", tile);
+        }
+    }
+
+    #[test]
+    fn halves_atlas_padding_per_level() {
+        let levels = Texture::generate_mip_chain(atlas(), Some((2, 2)), 2);
+        let padded = Texture::pad_atlas_tiles(levels, (2, 2), 2);
+
+        // Level 0 has 2x2 tiles with 2 pixel gutters, level 1 has 1x1 tiles with 1 pixel gutters
+        assert_eq!((padded[0].width, padded[0].height), (12, 12));
+        assert_eq!((padded[1].width, padded[1].height), (6, 6));
+
+        // The second level cells only contain their own (averaged) tile
+        let averages = [1.5, 11.5, 21.5, 31.5];
+        for (tile, average) in averages.iter().enumerate() {
+            let (cell_x, cell_y) = ((tile as u32 % 2) * 3, (tile as u32 / 2) * 3);
+            for y in 0..3 {
+                for x in 0..3 {
+                    assert_eq!(padded[1].pixel(cell_x + x, cell_y + y)[0], *average);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn half_floats_round_trip() {
+        for half in 0..=u16::MAX {
+            let value = f16_to_f32(half);
+            if value.is_nan() {
+                assert!(f16_to_f32(f32_to_f16(value)).is_nan());
+            } else {
+                assert_eq!(f32_to_f16(value), half, "{:#06x} ({})", half, value);
+            }
+        }
+    }
+
+    #[test]
+    fn converts_special_half_floats() {
+        assert_eq!(f32_to_f16(f32::INFINITY), 0x7c00);
+        assert_eq!(f32_to_f16(f32::NEG_INFINITY), 0xfc00);
+        assert!(f16_to_f32(f32_to_f16(f32::NAN)).is_nan());
+        assert_eq!(f32_to_f16(-0.0), 0x8000);
+
+        // Too large becomes infinity, too small becomes zero
+        assert_eq!(f32_to_f16(70000.0), 0x7c00);
+        assert_eq!(f32_to_f16(1e-10), 0x0000);
+
+        // The smallest subnormal and the largest normal value
+        assert_eq!(f32_to_f16(2f32.powi(-24)), 0x0001);
+        assert_eq!(f32_to_f16(65504.0), 0x7bff);
+    }
+
+    #[test]
+    fn rounds_half_floats_to_nearest_even() {
+        // Halfway between 1.0 (even) and the next half float rounds down
+        assert_eq!(f32_to_f16(1.0 + 2f32.powi(-11)), 0x3c00);
+        // Halfway between an odd and an even mantissa rounds up to the even one
+        assert_eq!(f32_to_f16(1.0 + 3.0 * 2f32.powi(-11)), 0x3c02);
+        // Just above halfway rounds up
+        assert_eq!(f32_to_f16(1.0 + 2f32.powi(-11) + 2f32.powi(-20)), 0x3c01);
+
+        // The same for subnormals, 0.5 and 1.5 of the smallest subnormal
+        assert_eq!(f32_to_f16(2f32.powi(-25)), 0x0000);
+        assert_eq!(f32_to_f16(3.0 * 2f32.powi(-25)), 0x0002);
+
+        // Rounding up the largest mantissa carries into the exponent
+        assert_eq!(f32_to_f16(2.0 - 2f32.powi(-12)), 0x4000);
+        assert_eq!(f32_to_f16(65520.0), 0x7c00);
+    }
 }
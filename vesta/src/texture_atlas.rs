@@ -0,0 +1,337 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use anyhow::*;
+use cgmath::Vector2;
+use image::RgbaImage;
+use serde::{Deserialize, Serialize};
+
+use crate::{Renderer, Texture, TextureConfig};
+
+/// A rectangle within an atlas, in pixels
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AtlasRect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// A rectangle within an atlas, in texture coordinates
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct UvRect {
+    pub min: Vector2<f32>,
+    pub max: Vector2<f32>,
+}
+
+impl UvRect {
+    /// Map a texture coordinate relative to this rectangle (0 to 1 on both axes) to a
+    /// texture coordinate within the atlas
+    pub fn map(&self, uv: Vector2<f32>) -> Vector2<f32> {
+        Vector2::new(
+            self.min.x + (self.max.x - self.min.x) * uv.x,
+            self.min.y + (self.max.y - self.min.y) * uv.y,
+        )
+    }
+
+    pub fn size(&self) -> Vector2<f32> {
+        self.max - self.min
+    }
+}
+
+/// Where each image was placed within an atlas. This can be saved alongside the atlas image
+/// so atlases can be packed at build time and loaded at runtime
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct TextureAtlasLayout {
+    pub width: u32,
+    pub height: u32,
+    pub rects: BTreeMap<String, AtlasRect>,
+}
+
+impl TextureAtlasLayout {
+    /// The pixel rectangle of the named image (not including its gutter)
+    pub fn rect(&self, name: &str) -> Option<AtlasRect> {
+        self.rects.get(name).copied()
+    }
+
+    /// The texture coordinates of the named image
+    pub fn uv_rect(&self, name: &str) -> Option<UvRect> {
+        let rect = self.rects.get(name)?;
+
+        Some(UvRect {
+            min: Vector2::new(
+                rect.x as f32 / self.width as f32,
+                rect.y as f32 / self.height as f32,
+            ),
+            max: Vector2::new(
+                (rect.x + rect.width) as f32 / self.width as f32,
+                (rect.y + rect.height) as f32 / self.height as f32,
+            ),
+        })
+    }
+
+    /// Save this layout as JSON
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let json = serde_json::to_string_pretty(self)?;
+        std::fs::write(path, json)?;
+        Ok(())
+    }
+
+    /// Load a layout previously saved with `save`
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::from_json(&std::fs::read_to_string(path)?)
+    }
+
+    /// Parse a layout from JSON, useful with `include_str!`
+    pub fn from_json(json: &str) -> Result<Self> {
+        Ok(serde_json::from_str(json)?)
+    }
+}
+
+/// A packed atlas image and its layout
+pub struct TextureAtlas {
+    pub image: RgbaImage,
+    pub layout: TextureAtlasLayout,
+}
+
+impl TextureAtlas {
+    /// Save the atlas image (format picked from the extension) and its layout
+    pub fn save<P: AsRef<Path>, L: AsRef<Path>>(
+        &self,
+        image_path: P,
+        layout_path: L,
+    ) -> Result<()> {
+        self.image.save(image_path)?;
+        self.layout.save(layout_path)
+    }
+}
+
+/// Packs many images into a single texture
+pub struct TextureAtlasBuilder {
+    images: Vec<(String, RgbaImage)>,
+    padding: u32,
+    alignment: u32,
+    max_size: u32,
+}
+
+impl TextureAtlasBuilder {
+    pub fn new() -> Self {
+        Self {
+            images: Vec::new(),
+            padding: 1,
+            alignment: 1,
+            max_size: 8192,
+        }
+    }
+
+    /// The amount of pixels around each image. This gutter is filled by extending the edge
+    /// pixels of the image, so filtering never samples neighbouring images. Defaults to 1
+    pub fn with_padding(&mut self, padding: u32) -> &mut Self {
+        self.padding = padding;
+        self
+    }
+
+    /// Place images (and size their gutters) at multiples of this many pixels. Use
+    /// `2^(levels - 1)` so images stay separated for the first `levels` mip levels
+    pub fn with_alignment(&mut self, alignment: u32) -> &mut Self {
+        self.alignment = alignment.max(1).next_power_of_two();
+        self
+    }
+
+    /// The maximum width and height of the atlas, packing fails if the images do not fit
+    pub fn with_max_size(&mut self, max_size: u32) -> &mut Self {
+        self.max_size = max_size;
+        self
+    }
+
+    /// Add an image to the atlas
+    pub fn add_image<S: Into<String>>(
+        &mut self,
+        name: S,
+        image: &image::DynamicImage,
+    ) -> &mut Self {
+        self.images.push((name.into(), image.to_rgba8()));
+        self
+    }
+
+    /// Add an encoded image (e.g. a PNG) to the atlas
+    pub fn add_image_bytes<S: Into<String>>(&mut self, name: S, bytes: &[u8]) -> Result<&mut Self> {
+        let image = image::load_from_memory(bytes)?;
+        Ok(self.add_image(name, &image))
+    }
+
+    /// Pack the images into an atlas. The atlas is always a power of two in size
+    pub fn pack(&self) -> Result<TextureAtlas> {
+        if self.images.is_empty() {
+            bail!("Texture atlas has no images");
+        }
+
+        for (i, (name, image)) in self.images.iter().enumerate() {
+            if self.images[..i].iter().any(|(other, _)| other == name) {
+                bail!("Texture atlas already contains an image named '{}'", name);
+            }
+
+            if image.width() == 0 || image.height() == 0 {
+                bail!("Texture atlas image '{}' is empty", name);
+            }
+        }
+
+        let gutter = align(self.padding, self.alignment);
+        let cell_size = |image: &RgbaImage| {
+            (
+                align(image.width(), self.alignment) + gutter * 2,
+                align(image.height(), self.alignment) + gutter * 2,
+            )
+        };
+
+        // Tallest first packs shelves most tightly
+        let mut order: Vec<usize> = (0..self.images.len()).collect();
+        order.sort_by_key(|i| std::cmp::Reverse(cell_size(&self.images[*i].1).1));
+        let cells: Vec<(u32, u32)> = order
+            .iter()
+            .map(|i| cell_size(&self.images[*i].1))
+            .collect();
+
+        let area: u64 = cells.iter().map(|(w, h)| *w as u64 * *h as u64).sum();
+        let largest = cells.iter().map(|(w, h)| *w.max(h)).max().unwrap();
+        let start = ((area as f64).sqrt().ceil() as u32)
+            .max(largest)
+            .next_power_of_two();
+
+        // Grow the atlas until everything fits
+        let (mut width, mut height) = (start, start);
+        let positions = loop {
+            if width > self.max_size || height > self.max_size {
+                bail!(
+                    "Texture atlas images do not fit within {}x{}",
+                    self.max_size,
+                    self.max_size
+                );
+            }
+
+            if let Some(positions) = pack_shelves(&cells, width, height) {
+                break positions;
+            }
+
+            if width <= height {
+                width *= 2;
+            } else {
+                height *= 2;
+            }
+        };
+
+        let mut atlas = RgbaImage::new(width, height);
+        let mut rects = BTreeMap::new();
+
+        for (i, (cell_x, cell_y)) in order.iter().zip(positions) {
+            let (name, image) = &self.images[*i];
+            let (cell_width, cell_height) = cell_size(image);
+            let (image_x, image_y) = (cell_x + gutter, cell_y + gutter);
+
+            // Copy the image, extending its edges into the gutter
+            for y in 0..cell_height {
+                for x in 0..cell_width {
+                    let source_x = (x as i64 - gutter as i64).clamp(0, image.width() as i64 - 1);
+                    let source_y = (y as i64 - gutter as i64).clamp(0, image.height() as i64 - 1);
+                    atlas.put_pixel(
+                        cell_x + x,
+                        cell_y + y,
+                        *image.get_pixel(source_x as u32, source_y as u32),
+                    );
+                }
+            }
+
+            rects.insert(
+                name.clone(),
+                AtlasRect {
+                    x: image_x,
+                    y: image_y,
+                    width: image.width(),
+                    height: image.height(),
+                },
+            );
+        }
+
+        Ok(TextureAtlas {
+            image: atlas,
+            layout: TextureAtlasLayout {
+                width,
+                height,
+                rects,
+            },
+        })
+    }
+
+    /// Pack the images and upload the atlas as a texture
+    pub fn build(
+        &self,
+        renderer: &Renderer,
+        label: Option<&str>,
+        config: TextureConfig,
+    ) -> Result<(Texture, TextureAtlasLayout)> {
+        let atlas = self.pack()?;
+        let texture = renderer.create_texture_from_image(
+            &image::DynamicImage::ImageRgba8(atlas.image),
+            label,
+            config,
+        )?;
+
+        Ok((texture, atlas.layout))
+    }
+}
+
+impl Default for TextureAtlasBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Renderer {
+    /// Create a texture from a previously packed atlas
+    pub fn create_texture_atlas(
+        &self,
+        atlas: &TextureAtlas,
+        label: Option<&str>,
+        config: TextureConfig,
+    ) -> Result<Texture> {
+        if atlas.image.dimensions() != (atlas.layout.width, atlas.layout.height) {
+            bail!("Texture atlas image does not match the size of its layout");
+        }
+
+        self.create_texture_from_image(
+            &image::DynamicImage::ImageRgba8(atlas.image.clone()),
+            label,
+            config,
+        )
+    }
+}
+
+/// Round up to a multiple of alignment (a power of two)
+fn align(value: u32, alignment: u32) -> u32 {
+    (value + alignment - 1) & !(alignment - 1)
+}
+
+/// Place cells left to right in rows (shelves), returning the position of each cell or None
+/// if they do not fit
+fn pack_shelves(cells: &[(u32, u32)], width: u32, height: u32) -> Option<Vec<(u32, u32)>> {
+    let mut positions = Vec::with_capacity(cells.len());
+    let (mut x, mut y, mut shelf_height) = (0, 0, 0);
+
+    for (cell_width, cell_height) in cells {
+        if x + cell_width > width {
+            x = 0;
+            y += shelf_height;
+            shelf_height = 0;
+        }
+
+        if x + cell_width > width || y + cell_height > height {
+            return None;
+        }
+
+        positions.push((x, y));
+        x += cell_width;
+        shelf_height = shelf_height.max(*cell_height);
+    }
+
+    Some(positions)
+}
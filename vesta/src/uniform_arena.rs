@@ -0,0 +1,235 @@
+use std::marker::PhantomData;
+use std::num::NonZeroU64;
+use std::sync::{Arc, Mutex};
+
+use crevice::std140::{AsStd140, Std140};
+
+use crate::{Renderer, UniformBufferUtils};
+
+/// A slot allocated in a `UniformArena`. The slot is returned to the arena when this is dropped
+pub struct UniformSlot {
+    index: u32,
+    offset: u32,
+    free_slots: Arc<Mutex<Vec<u32>>>,
+}
+
+impl UniformSlot {
+    /// The dynamic offset to pass to `set_bind_group` for this slot
+    pub fn offset(&self) -> u32 {
+        self.offset
+    }
+}
+
+impl Drop for UniformSlot {
+    fn drop(&mut self) {
+        if let Ok(mut free_slots) = self.free_slots.lock() {
+            free_slots.push(self.index);
+        }
+    }
+}
+
+/// Many uniforms of the same type packed into one buffer, sharing a single bind group that
+/// uses dynamic offsets. Use this instead of a `UniformBuffer` per object when there are lots
+/// of objects (e.g. a model matrix per chunk). The bind group layout must be created with
+/// `UniformBufferUtils::create_dynamic_bind_group_layout`
+pub struct UniformArena<T>
+where
+    T: AsStd140,
+{
+    pub buffer: wgpu::Buffer,
+    pub bind_group: wgpu::BindGroup,
+    name: String,
+    visibility: wgpu::ShaderStages,
+    slot_size: u32,
+    capacity: u32,
+    next_slot: u32,
+    free_slots: Arc<Mutex<Vec<u32>>>,
+    _marker: PhantomData<T>,
+}
+
+impl Renderer {
+    /// Create a uniform arena with room for `capacity` slots, it grows when full
+    pub fn create_uniform_arena<T: AsStd140>(
+        &self,
+        name: &str,
+        visibility: wgpu::ShaderStages,
+        capacity: u32,
+    ) -> UniformArena<T> {
+        UniformArena::new(name, visibility, capacity, self)
+    }
+}
+
+impl<T: AsStd140> UniformArena<T> {
+    pub fn new(
+        name: &str,
+        visibility: wgpu::ShaderStages,
+        capacity: u32,
+        renderer: &Renderer,
+    ) -> Self {
+        // Each slot must start on a multiple of the device's offset alignment (a power of two)
+        let alignment = renderer.device.limits().min_uniform_buffer_offset_alignment;
+        let size = T::std140_size_static() as u32;
+        let slot_size = (size + alignment - 1) & !(alignment - 1);
+
+        let capacity = capacity.max(1);
+        let (buffer, bind_group) =
+            Self::create_buffer(name, visibility, slot_size, capacity, renderer);
+
+        Self {
+            buffer,
+            bind_group,
+            name: name.to_string(),
+            visibility,
+            slot_size,
+            capacity,
+            next_slot: 0,
+            free_slots: Arc::new(Mutex::new(Vec::new())),
+            _marker: PhantomData,
+        }
+    }
+
+    fn create_buffer(
+        name: &str,
+        visibility: wgpu::ShaderStages,
+        slot_size: u32,
+        capacity: u32,
+        renderer: &Renderer,
+    ) -> (wgpu::Buffer, wgpu::BindGroup) {
+        let buffer = renderer.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some(name),
+            size: slot_size as wgpu::BufferAddress * capacity as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM
+                | wgpu::BufferUsages::COPY_DST
+                | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+
+        // Shaders see a single slot, the dynamic offset picks which one
+        let bind_group = renderer
+            .device
+            .create_bind_group(&wgpu::BindGroupDescriptor {
+                layout: &renderer.get_bind_group_layout(
+                    "uniform_dynamic_bind_group_layout",
+                    &[UniformBufferUtils::dynamic_layout_entry(visibility)],
+                ),
+                entries: &[wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                        buffer: &buffer,
+                        offset: 0,
+                        size: NonZeroU64::new(T::std140_size_static() as u64),
+                    }),
+                }],
+                label: Some("uniform_arena_bind_group"),
+            });
+
+        (buffer, bind_group)
+    }
+
+    /// Allocate a slot and write the data to it. If the arena is full it doubles in size, which
+    /// recreates the buffer and bind group (existing slots keep their data and offsets)
+    pub fn allocate(&mut self, data: &T, renderer: &Renderer) -> UniformSlot {
+        let reused = self.free_slots.lock().ok().and_then(|mut free| free.pop());
+
+        let index = match reused {
+            Some(index) => index,
+            None => {
+                if self.next_slot == self.capacity {
+                    self.grow(self.capacity * 2, renderer);
+                }
+
+                self.next_slot += 1;
+                self.next_slot - 1
+            }
+        };
+
+        let slot = UniformSlot {
+            index,
+            offset: index * self.slot_size,
+            free_slots: self.free_slots.clone(),
+        };
+        self.write(&slot, data, renderer);
+
+        slot
+    }
+
+    /// Write new data into a slot allocated from this arena
+    pub fn write(&self, slot: &UniformSlot, data: &T, renderer: &Renderer) {
+        self.debug_assert_owns(slot);
+        renderer.queue.write_buffer(
+            &self.buffer,
+            slot.offset as wgpu::BufferAddress,
+            data.as_std140().as_bytes(),
+        );
+    }
+
+    /// Set the arena's bind group at the slot's offset
+    pub fn bind<'a>(
+        &'a self,
+        render_pass: &mut wgpu::RenderPass<'a>,
+        index: u32,
+        slot: &UniformSlot,
+    ) {
+        self.debug_assert_owns(slot);
+        render_pass.set_bind_group(index, &self.bind_group, &[slot.offset]);
+    }
+
+    /// Slots from another arena would silently use that arena's offsets in this buffer. Each
+    /// arena has its own free list, which its slots share
+    fn debug_assert_owns(&self, slot: &UniformSlot) {
+        debug_assert!(
+            Arc::ptr_eq(&self.free_slots, &slot.free_slots),
+            "Uniform slot was allocated from a different arena than '{}'",
+            self.name
+        );
+    }
+
+    fn grow(&mut self, capacity: u32, renderer: &Renderer) {
+        let (buffer, bind_group) = Self::create_buffer(
+            &self.name,
+            self.visibility,
+            self.slot_size,
+            capacity,
+            renderer,
+        );
+
+        // Copy the existing slots across
+        let mut encoder = renderer
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Uniform Arena Grow Encoder"),
+            });
+        encoder.copy_buffer_to_buffer(
+            &self.buffer,
+            0,
+            &buffer,
+            0,
+            self.slot_size as wgpu::BufferAddress * self.capacity as wgpu::BufferAddress,
+        );
+        renderer.queue.submit(std::iter::once(encoder.finish()));
+
+        self.buffer = buffer;
+        self.bind_group = bind_group;
+        self.capacity = capacity;
+    }
+
+    /// The number of slots the arena can hold before it has to grow
+    pub fn capacity(&self) -> u32 {
+        self.capacity
+    }
+
+    /// The number of slots currently allocated
+    pub fn len(&self) -> u32 {
+        let free = self.free_slots.lock().map(|free| free.len()).unwrap_or(0);
+        self.next_slot - free as u32
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The distance in bytes between slots
+    pub fn slot_size(&self) -> u32 {
+        self.slot_size
+    }
+}
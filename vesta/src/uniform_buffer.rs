@@ -8,6 +8,8 @@ use wgpu::util::DeviceExt;
 
 use crevice::std140::AsStd140;
 
+use crate::Renderer;
+
 #[repr(C)]
 #[derive(Copy, Clone, Debug, AsStd140)]
 pub struct ModelUniform {
@@ -49,7 +51,7 @@ where
     pub bind_group: wgpu::BindGroup,
 }
 
-impl crate::Renderer {
+impl Renderer {
     /// Write the specified uniform buffer to the GPU
     pub fn write_uniform_buffer<T: Pod + AsStd140>(&self, uniform_buffer: &UniformBuffer<T>) {
         self.queue.write_buffer(
@@ -62,23 +64,28 @@ impl crate::Renderer {
 
 impl<T: Pod + AsStd140> UniformBuffer<T> {
     /// Crate a new uniform buffer to store data of type
-    pub fn new(name: &str, visibility: wgpu::ShaderStages, data: T, device: &wgpu::Device) -> Self {
+    pub fn new(name: &str, visibility: wgpu::ShaderStages, data: T, renderer: &Renderer) -> Self {
         // Create the actual buffer
-        let buffer = device.create_buffer_init(&BufferInitDescriptor {
+        let buffer = renderer.device.create_buffer_init(&BufferInitDescriptor {
             label: Some(name),
             contents: cast_slice(&[data.as_std140()]),
             usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::UNIFORM,
         });
 
         // Create the binding group for this uniform buffer
-        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            layout: &UniformBufferUtils::create_bind_group_layout(visibility, device),
-            entries: &[wgpu::BindGroupEntry {
-                binding: 0,
-                resource: buffer.as_entire_binding(),
-            }],
-            label: Some("uniform_bind_group"),
-        });
+        let bind_group = renderer
+            .device
+            .create_bind_group(&wgpu::BindGroupDescriptor {
+                layout: &renderer.get_bind_group_layout(
+                    "uniform_bind_group_layout",
+                    &[UniformBufferUtils::layout_entry(visibility)],
+                ),
+                entries: &[wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: buffer.as_entire_binding(),
+                }],
+                label: Some("uniform_bind_group"),
+            });
 
         Self {
             data,
@@ -90,22 +97,76 @@ impl<T: Pod + AsStd140> UniformBuffer<T> {
 
 pub struct UniformBufferUtils {}
 impl UniformBufferUtils {
+    /// The layout entry of a uniform buffer at binding 0
+    pub fn layout_entry(visibility: wgpu::ShaderStages) -> wgpu::BindGroupLayoutEntry {
+        Self::buffer_layout_entry(visibility, wgpu::BufferBindingType::Uniform, false)
+    }
+
+    /// The layout entry of a uniform buffer at binding 0 that is bound with a dynamic offset
+    pub fn dynamic_layout_entry(visibility: wgpu::ShaderStages) -> wgpu::BindGroupLayoutEntry {
+        Self::buffer_layout_entry(visibility, wgpu::BufferBindingType::Uniform, true)
+    }
+
+    /// The layout entry of a storage buffer at binding 0
+    pub fn storage_layout_entry(
+        visibility: wgpu::ShaderStages,
+        read_only: bool,
+    ) -> wgpu::BindGroupLayoutEntry {
+        Self::buffer_layout_entry(
+            visibility,
+            wgpu::BufferBindingType::Storage { read_only },
+            false,
+        )
+    }
+
+    fn buffer_layout_entry(
+        visibility: wgpu::ShaderStages,
+        ty: wgpu::BufferBindingType,
+        has_dynamic_offset: bool,
+    ) -> wgpu::BindGroupLayoutEntry {
+        wgpu::BindGroupLayoutEntry {
+            binding: 0,
+            visibility,
+            ty: wgpu::BindingType::Buffer {
+                ty,
+                has_dynamic_offset,
+                min_binding_size: None,
+            },
+            count: None,
+        }
+    }
+
     pub fn create_bind_group_layout(
         visibility: wgpu::ShaderStages,
         device: &wgpu::Device,
     ) -> wgpu::BindGroupLayout {
         device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-            entries: &[wgpu::BindGroupLayoutEntry {
-                binding: 0,
-                visibility,
-                ty: wgpu::BindingType::Buffer {
-                    ty: wgpu::BufferBindingType::Uniform,
-                    has_dynamic_offset: false,
-                    min_binding_size: None,
-                },
-                count: None,
-            }],
+            entries: &[Self::layout_entry(visibility)],
             label: Some("uniform_bind_group_layout"),
         })
     }
+
+    /// The layout of a uniform bind group that is bound with a dynamic offset, see `UniformArena`
+    pub fn create_dynamic_bind_group_layout(
+        visibility: wgpu::ShaderStages,
+        device: &wgpu::Device,
+    ) -> wgpu::BindGroupLayout {
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[Self::dynamic_layout_entry(visibility)],
+            label: Some("uniform_dynamic_bind_group_layout"),
+        })
+    }
+
+    /// The layout of a storage buffer bind group, see `StorageBuffer`. Read only buffers can
+    /// also be used in vertex shaders
+    pub fn create_storage_bind_group_layout(
+        visibility: wgpu::ShaderStages,
+        read_only: bool,
+        device: &wgpu::Device,
+    ) -> wgpu::BindGroupLayout {
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[Self::storage_layout_entry(visibility, read_only)],
+            label: Some("storage_bind_group_layout"),
+        })
+    }
 }
@@ -1,7 +1,74 @@
-use cgmath::{Vector2, Vector3};
+use cgmath::{Matrix2, Matrix3, Matrix4, Point2, Point3, Vector2, Vector3, Vector4};
+
+pub use vesta_derive::VertexLayout;
+
+/// Describes how a vertex type is laid out in a vertex buffer. This is usually derived:
+///
+/// ```ignore
+/// #[repr(C)]
+/// #[derive(Copy, Clone, vesta::VertexLayout)]
+/// pub struct SpriteVertex {
+///     pub position: Vector2<f32>,
+///     #[vertex(format = "Unorm8x4")]
+///     pub color: [u8; 4],
+/// }
+/// ```
+pub trait VertexLayout: bytemuck::Pod {
+    fn layout<'a>() -> wgpu::VertexBufferLayout<'a>;
+}
+
+/// Maps a field type to the vertex format(s) used to read it in a shader. Matrices are
+/// read as one attribute per column
+pub trait VertexAttributeFormat {
+    const FORMATS: &'static [wgpu::VertexFormat];
+}
+
+macro_rules! impl_vertex_attribute_format {
+    ($($ty:ty => [$($format:ident),+]),+ $(,)?) => {
+        $(
+            impl VertexAttributeFormat for $ty {
+                const FORMATS: &'static [wgpu::VertexFormat] = &[$(wgpu::VertexFormat::$format),+];
+            }
+        )+
+    };
+}
+
+impl_vertex_attribute_format!(
+    f32 => [Float32],
+    [f32; 2] => [Float32x2],
+    [f32; 3] => [Float32x3],
+    [f32; 4] => [Float32x4],
+    u32 => [Uint32],
+    [u32; 2] => [Uint32x2],
+    [u32; 3] => [Uint32x3],
+    [u32; 4] => [Uint32x4],
+    i32 => [Sint32],
+    [i32; 2] => [Sint32x2],
+    [i32; 3] => [Sint32x3],
+    [i32; 4] => [Sint32x4],
+    [u16; 2] => [Uint16x2],
+    [u16; 4] => [Uint16x4],
+    [u8; 2] => [Uint8x2],
+    [u8; 4] => [Uint8x4],
+    Vector2<f32> => [Float32x2],
+    Vector3<f32> => [Float32x3],
+    Vector4<f32> => [Float32x4],
+    Point2<f32> => [Float32x2],
+    Point3<f32> => [Float32x3],
+    Matrix2<f32> => [Float32x2, Float32x2],
+    Matrix3<f32> => [Float32x3, Float32x3, Float32x3],
+    Matrix4<f32> => [Float32x4, Float32x4, Float32x4, Float32x4],
+);
+
+/// Used by the `VertexLayout` derive to calculate `#[repr(C)]` field offsets, alignment
+/// is always a power of two
+#[doc(hidden)]
+pub const fn align_vertex_offset(offset: u64, align: u64) -> u64 {
+    (offset + align - 1) & !(align - 1)
+}
 
 #[repr(C)]
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, VertexLayout)]
 pub struct Vertex {
     pub position: Vector3<f32>,
     pub color: Vector3<f32>,
@@ -42,35 +109,4 @@ impl Vertex {
             normal: Vector3::new(0.0, 0.0, 0.0),
         }
     }
-
-    pub(crate) fn layout<'a>() -> wgpu::VertexBufferLayout<'a> {
-        wgpu::VertexBufferLayout {
-            array_stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
-            step_mode: wgpu::VertexStepMode::Vertex,
-            attributes: &[
-                wgpu::VertexAttribute {
-                    offset: 0,
-                    shader_location: 0,
-                    format: wgpu::VertexFormat::Float32x3,
-                },
-                wgpu::VertexAttribute {
-                    offset: std::mem::size_of::<Vector3<f32>>() as wgpu::BufferAddress,
-                    shader_location: 1,
-                    format: wgpu::VertexFormat::Float32x3,
-                },
-                wgpu::VertexAttribute {
-                    offset: (std::mem::size_of::<Vector3<f32>>() * 2) as wgpu::BufferAddress,
-                    shader_location: 2,
-                    format: wgpu::VertexFormat::Float32x2,
-                },
-                wgpu::VertexAttribute {
-                    offset: (std::mem::size_of::<Vector3<f32>>() * 2
-                        + std::mem::size_of::<Vector2<f32>>())
-                        as wgpu::BufferAddress,
-                    shader_location: 3,
-                    format: wgpu::VertexFormat::Float32x3,
-                },
-            ],
-        }
-    }
 }